@@ -0,0 +1,369 @@
+//! Derive macros for [`nosqlite`](https://github.com/HiruNya/nosqlite).
+//!
+//! Use these through `nosqlite` with the `derive` feature instead of depending on this crate directly.
+#![warn(missing_docs)]
+#![allow(clippy::tabs_in_doc_comments)]
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{Attribute, Data, DeriveInput, Error, Fields as SynFields, Ident, LitStr, parse_macro_input, Result, Token, Type,
+	ext::IdentExt, meta::ParseNestedMeta};
+
+/// Creates an associated function for each field of a struct that refers to it in a JSON object.
+///
+/// Each function returns a `TypedField` so comparisons only accept values of the field's type.
+/// A field whose type also derives `Fields` can be marked with `#[fields(nested)]`
+/// so that its own fields can be referred to, and a field can be left out using `#[fields(skip)]`.
+/// The names of the fields follow serde's `rename`, `rename_all`, `skip` and `flatten` attributes.
+///
+/// A struct called `{Name}Fields`, with the same visibility as the struct, is also created
+/// to refer to the fields of a nested struct.
+/// It can be given a different name using `#[fields(name = "...")]` on the struct,
+/// such as when `{Name}Fields` is already used.
+///
+/// # Example
+///
+/// ```
+/// # use nosqlite::{Connection, Fields, Filter, json};
+/// # use serde::{Deserialize, Serialize};
+/// # let connection = Connection::in_memory()?;
+/// # let table = connection.table("people")?;
+/// #[derive(Deserialize, Fields, Serialize)]
+/// struct Person {
+/// 	name: String,
+/// 	age: u8,
+/// 	#[fields(nested)]
+/// 	address: Address,
+/// }
+/// #[derive(Deserialize, Fields, Serialize)]
+/// #[serde(rename_all = "camelCase")]
+/// struct Address {
+/// 	city: String,
+/// 	post_code: u32,
+/// }
+/// table.insert(json!({"name": "Hiruna", "age": 19, "address": {"city": "Auckland", "postCode": 1010}}), &connection)?;
+/// table.insert(json!({"name": "Bob", "age": 13, "address": {"city": "Wellington", "postCode": 6011}}), &connection)?;
+/// let people: Vec<Person> = table.iter()
+/// 	.filter(Person::age().gt(18).and(Person::address().city().eq("Auckland")))
+/// 	.data(&connection)?;
+/// assert_eq!(people.len(), 1);
+/// assert_eq!(Person::address().post_code().path(), "$.address.postCode");
+/// # nosqlite::Result::Ok(())
+/// ```
+///
+/// Names that need to be quoted, such as ones with spaces or quotes in them, are escaped in the JSON path.
+///
+/// ```
+/// # use nosqlite::{Connection, Fields, json};
+/// # use serde::Serialize;
+/// # let connection = Connection::in_memory()?;
+/// # let table = connection.table("quotes")?;
+/// #[derive(Fields, Serialize)]
+/// #[fields(name = "QuotePaths")]
+/// struct Quote {
+/// 	#[serde(rename = "said by")]
+/// 	said_by: String,
+/// 	#[serde(rename = "\"text\"")]
+/// 	text: String,
+/// }
+/// assert_eq!(Quote::said_by().path(), "$.\"said by\"");
+/// table.insert(Quote { said_by: "Hiruna".into(), text: "Hello".into() }, &connection)?;
+/// assert_eq!(table.iter().filter(Quote::text().eq("Hello")).count(&connection)?, 1);
+/// assert_eq!(table.iter().filter(Quote::said_by().eq("Hiruna")).count(&connection)?, 1);
+/// let _: fn(String) -> QuotePaths = <Quote as Fields>::fields;
+/// # nosqlite::Result::Ok(())
+/// ```
+///
+/// Typos in the name of a field and comparisons with the wrong type are compile errors.
+///
+/// ```compile_fail
+/// # use nosqlite::Fields;
+/// #[derive(Fields)]
+/// struct Person {
+/// 	age: u8,
+/// }
+/// Person::aeg();
+/// ```
+///
+/// ```compile_fail
+/// # use nosqlite::Fields;
+/// #[derive(Fields)]
+/// struct Person {
+/// 	age: u8,
+/// }
+/// Person::age().gt("eighteen");
+/// ```
+#[proc_macro_derive(Fields, attributes(fields))]
+pub fn derive_fields(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	expand(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// A field of the struct that can be referred to.
+struct Member {
+	ident: Ident,
+	ty: Type,
+	/// The name of the field in the JSON object, or `None` if it is flattened.
+	name: Option<String>,
+	nested: bool,
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+	if !input.generics.params.is_empty() {
+		return Err(Error::new_spanned(&input.generics, "Fields can't be derived for generic structs"));
+	}
+	let named = match &input.data {
+		Data::Struct(data) => match &data.fields {
+			SynFields::Named(named) => named,
+			_ => return Err(Error::new(Span::call_site(), "Fields can only be derived for structs with named fields")),
+		},
+		_ => return Err(Error::new(Span::call_site(), "Fields can only be derived for structs")),
+	};
+	let container = ContainerAttributes::parse(&input.attrs)?;
+	let mut members = Vec::new();
+	for field in named.named.iter() {
+		let ident = field.ident.clone().expect("named fields have identifiers");
+		let attributes = FieldAttributes::parse(&field.attrs)?;
+		if attributes.skip || (attributes.flatten && !attributes.nested) {
+			continue;
+		}
+		let name = if attributes.flatten {
+			None
+		} else {
+			Some(attributes.rename.unwrap_or_else(|| rename(&ident.unraw().to_string(), container.rename_all.as_deref())))
+		};
+		members.push(Member { ident, ty: field.ty.clone(), name, nested: attributes.nested });
+	}
+
+	let vis = &input.vis;
+	let ident = &input.ident;
+	let fields_ident = container.name.unwrap_or_else(|| format_ident!("{}Fields", ident));
+	let root = members.iter().map(|member| {
+		let path = json_path("$", member)?;
+		Ok(accessor(vis, member, quote!(), quote!(#path)))
+	}).collect::<Result<Vec<_>>>()?;
+	let nested = members.iter().map(|member| {
+		let segment = json_path("", member)?;
+		Ok(accessor(vis, member, quote!(&self), quote!(format!("{}{}", self.path, #segment))))
+	}).collect::<Result<Vec<_>>>()?;
+	let documentation = format!("Refers to the fields of a [`{}`] that is inside of a JSON object.", ident);
+	Ok(quote! {
+		impl #ident {
+			#(#root)*
+		}
+
+		#[doc = #documentation]
+		#vis struct #fields_ident {
+			path: ::std::string::String,
+		}
+		impl #fields_ident {
+			#(#nested)*
+		}
+		impl ::nosqlite::Key for #fields_ident {
+			fn key(&self, data_key: &str) -> ::std::string::String {
+				::nosqlite::Key::key(&::nosqlite::Field(self.path.clone()), data_key)
+			}
+			fn json_type(&self, data_key: &str) -> ::std::option::Option<::std::string::String> {
+				::nosqlite::Key::json_type(&::nosqlite::Field(self.path.clone()), data_key)
+			}
+		}
+		impl ::nosqlite::Fields for #ident {
+			type Fields = #fields_ident;
+			fn fields(path: ::std::string::String) -> #fields_ident { #fields_ident { path } }
+		}
+	})
+}
+
+/// Creates the function that refers to a field, using the given receiver and JSON path.
+fn accessor(vis: &syn::Visibility, member: &Member, receiver: TokenStream2, path: TokenStream2) -> TokenStream2 {
+	let Member { ident, ty, name, nested } = member;
+	let documentation = match name {
+		Some(name) => format!("Refers to the `{}` field.", name),
+		None => format!("Refers to the fields that are flattened from `{}`.", ident.unraw()),
+	};
+	if *nested {
+		quote! {
+			#[doc = #documentation]
+			#vis fn #ident(#receiver) -> <#ty as ::nosqlite::Fields>::Fields {
+				<#ty as ::nosqlite::Fields>::fields(::std::string::String::from(#path))
+			}
+		}
+	} else {
+		quote! {
+			#[doc = #documentation]
+			#vis fn #ident(#receiver) -> ::nosqlite::TypedField<#ty> {
+				::nosqlite::TypedField::new(#path)
+			}
+		}
+	}
+}
+
+/// Adds the name of a field to a JSON path, quoting it if necessary.
+fn json_path(parent: &str, member: &Member) -> Result<String> {
+	let name = match &member.name {
+		None => return Ok(parent.into()),
+		Some(name) if is_plain(name) => return Ok(format!("{}.{}", parent, name)),
+		Some(name) => name,
+	};
+	// SQLite compares the name to the key as it is written in the JSON object, so it is escaped the same way
+	let escaped = escape(name);
+	if !escaped.contains('"') {
+		Ok(format!("{}.\"{}\"", parent, escaped))
+	} else if !escaped.contains(['.', '[']) {
+		// A quoted name ends at the first quote even if it is escaped, but a name that isn't quoted only ends at a `.` or `[`
+		Ok(format!("{}.{}", parent, escaped))
+	} else {
+		Err(Error::new_spanned(&member.ident, "SQLite can't refer to a name with both a quote and a `.` or `[` in it"))
+	}
+}
+
+/// Escapes a string in the same way as serde_json, without the quotes around it.
+fn escape(name: &str) -> String {
+	name.chars().fold(String::new(), |mut escaped, c| {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\t' => escaped.push_str("\\t"),
+			'\u{8}' => escaped.push_str("\\b"),
+			'\u{c}' => escaped.push_str("\\f"),
+			c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+			c => escaped.push(c),
+		}
+		escaped
+	})
+}
+
+/// Whether the name can be used in a JSON path without quoting it.
+fn is_plain(name: &str) -> bool {
+	let mut chars = name.chars();
+	chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+		&& chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The attributes on a field that change how it is referred to.
+#[derive(Default)]
+struct FieldAttributes {
+	rename: Option<String>,
+	skip: bool,
+	flatten: bool,
+	nested: bool,
+}
+impl FieldAttributes {
+	fn parse(attributes: &[Attribute]) -> Result<Self> {
+		let mut parsed = Self::default();
+		for attribute in attributes {
+			if attribute.path().is_ident("fields") {
+				attribute.parse_nested_meta(|meta| {
+					if meta.path.is_ident("nested") {
+						parsed.nested = true;
+					} else if meta.path.is_ident("skip") {
+						parsed.skip = true;
+					} else {
+						return Err(meta.error("expected `nested` or `skip`"));
+					}
+					Ok(())
+				})?;
+			} else if attribute.path().is_ident("serde") {
+				attribute.parse_nested_meta(|meta| {
+					if meta.path.is_ident("rename") {
+						parsed.rename = serialize_name(&meta)?;
+					} else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+						parsed.skip = true;
+					} else if meta.path.is_ident("flatten") {
+						parsed.flatten = true;
+					} else {
+						skip_meta(&meta)?;
+					}
+					Ok(())
+				})?;
+			}
+		}
+		Ok(parsed)
+	}
+}
+
+/// The attributes on the struct that change how its fields are referred to.
+#[derive(Default)]
+struct ContainerAttributes {
+	/// The name of the struct that refers to the fields of a nested struct.
+	name: Option<Ident>,
+	rename_all: Option<String>,
+}
+impl ContainerAttributes {
+	fn parse(attributes: &[Attribute]) -> Result<Self> {
+		let mut parsed = Self::default();
+		for attribute in attributes {
+			if attribute.path().is_ident("fields") {
+				attribute.parse_nested_meta(|meta| {
+					if meta.path.is_ident("name") {
+						parsed.name = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+					} else {
+						return Err(meta.error("expected `name`"));
+					}
+					Ok(())
+				})?;
+			} else if attribute.path().is_ident("serde") {
+				attribute.parse_nested_meta(|meta| {
+					if meta.path.is_ident("rename_all") {
+						parsed.rename_all = serialize_name(&meta)?;
+					} else {
+						skip_meta(&meta)?;
+					}
+					Ok(())
+				})?;
+			}
+		}
+		Ok(parsed)
+	}
+}
+
+/// Reads the name used when serialising from either `rename = "..."` or `rename(serialize = "...")`.
+fn serialize_name(meta: &ParseNestedMeta) -> Result<Option<String>> {
+	if meta.input.peek(Token![=]) {
+		return Ok(Some(meta.value()?.parse::<LitStr>()?.value()));
+	}
+	let mut name = None;
+	meta.parse_nested_meta(|meta| {
+		let value = meta.value()?.parse::<LitStr>()?.value();
+		if meta.path.is_ident("serialize") {
+			name = Some(value);
+		}
+		Ok(())
+	})?;
+	Ok(name)
+}
+
+/// Skips over a serde attribute that doesn't change the name of a field.
+fn skip_meta(meta: &ParseNestedMeta) -> Result<()> {
+	if meta.input.peek(Token![=]) {
+		meta.value()?.parse::<syn::Expr>()?;
+	} else if meta.input.peek(syn::token::Paren) {
+		meta.input.parse::<proc_macro2::Group>()?;
+	}
+	Ok(())
+}
+
+/// Renames a field in the same way as serde's `rename_all` attribute.
+fn rename(name: &str, rule: Option<&str>) -> String {
+	let capitalise = |word: &str| {
+		let mut chars = word.chars();
+		chars.next().map(|c| c.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+	};
+	match rule {
+		Some("lowercase") => name.to_ascii_lowercase(),
+		Some("UPPERCASE") => name.to_ascii_uppercase(),
+		Some("PascalCase") => name.split('_').map(capitalise).collect(),
+		Some("camelCase") => {
+			let pascal: String = name.split('_').map(capitalise).collect();
+			let mut chars = pascal.chars();
+			chars.next().map(|c| c.to_ascii_lowercase().to_string() + chars.as_str()).unwrap_or_default()
+		},
+		Some("SCREAMING_SNAKE_CASE") => name.to_ascii_uppercase(),
+		Some("kebab-case") => name.replace('_', "-"),
+		Some("SCREAMING-KEBAB-CASE") => name.replace('_', "-").to_ascii_uppercase(),
+		_ => name.into(),
+	}
+}
@@ -0,0 +1,521 @@
+use futures_core::Stream as FuturesStream;
+use rusqlite::types::{FromSql, ToSql};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::oneshot;
+
+use std::{collections::VecDeque, future::Future, marker::PhantomData, panic::{AssertUnwindSafe, catch_unwind, resume_unwind},
+	path::Path, pin::Pin, sync::{Arc, mpsc as std_mpsc}, task::{Context, Poll}, thread};
+
+use crate::{Clause, Connection, Entry, Error, Filter, Iterator, Operation, Result, Sort, Table};
+
+/// The number of entries an [`AsyncStream`] reads at a time.
+///
+/// [`AsyncStream`]: struct.AsyncStream.html
+const STREAM_PAGE: u32 = 64;
+
+type Job = Box<dyn FnOnce(&Connection) + Send>;
+
+/// Reads a page of entries on the connection's thread, given how many entries to skip and take.
+type ReadPage<T> = dyn Fn(&Connection, u32, u32) -> Result<Vec<Result<T>>> + Send + Sync;
+
+/// A page of entries that is being read.
+type Page<T> = Pin<Box<dyn Future<Output = Result<Vec<Result<T>>>> + Send>>;
+
+/// A connection that runs its operations on a dedicated thread so that they don't block an async executor.
+///
+/// This needs the `tokio` feature.
+/// The async methods of [`Table`], [`Operation`] and [`Iterator`] end with `_async` and take this connection,
+/// and anything else can be run on the connection's thread using [`call`].
+/// Cloning the connection is cheap and the clones share the same thread,
+/// so operations run one at a time in the order they were started.
+///
+/// [`Table`]: struct.Table.html
+/// [`Operation`]: struct.Operation.html
+/// [`Iterator`]: struct.Iterator.html
+/// [`call`]: #method.call
+///
+/// # Example
+///
+/// ```
+/// # use nosqlite::{AsyncConnection, field, json, Key};
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> nosqlite::Result<()> {
+/// let connection = AsyncConnection::in_memory()?;
+/// let table = connection.table("people").await?;
+/// table.insert_async(json!({"name": "Hiruna", "age": 19}), &connection).await?;
+/// table.insert_async(json!({"name": "Bob", "age": 13}), &connection).await?;
+/// let names: Vec<String> = table.iter()
+/// 	.filter(field("age").gte(18))
+/// 	.field_async("name", &connection)
+/// 	.await?;
+/// assert_eq!(names, ["Hiruna"]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AsyncConnection {
+	jobs: std_mpsc::Sender<Job>,
+}
+impl AsyncConnection {
+	/// Moves a connection onto its own thread.
+	pub fn new(connection: Connection) -> Self {
+		let (jobs, receiver) = std_mpsc::channel::<Job>();
+		// The thread stops once every clone of the connection has been dropped
+		thread::spawn(move || receiver.into_iter().for_each(|job| job(&connection)));
+		Self { jobs }
+	}
+
+	/// Opens a connection to a sqlite database, creating one if it doesn't exist.
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+		Ok(Self::new(Connection::open(path)?))
+	}
+
+	/// Opens a new connection to a sqlite database in-memory.
+	pub fn in_memory() -> Result<Self> {
+		Ok(Self::new(Connection::in_memory()?))
+	}
+
+	/// Runs the closure on the connection's thread and waits for it to finish.
+	///
+	/// If the closure panics, the panic is resumed in the task that is waiting for it.
+	/// Returns [`Error::Disconnected`] if the connection's thread has stopped.
+	///
+	/// [`Error::Disconnected`]: enum.Error.html#variant.Disconnected
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{AsyncConnection, json};
+	/// # #[tokio::main(flavor = "current_thread")]
+	/// # async fn main() -> nosqlite::Result<()> {
+	/// let connection = AsyncConnection::in_memory()?;
+	/// let table = connection.table("people").await?;
+	/// let ids = connection.call(move |connection| connection.transaction(|tx| {
+	/// 	table.insert(json!({"name": "Hiruna"}), tx)?;
+	/// 	table.insert(json!({"name": "Bob"}), tx)?;
+	/// 	table.iter().id(tx)
+	/// })).await?;
+	/// assert_eq!(ids, [1, 2]);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub async fn call<T, F>(&self, f: F) -> Result<T>
+	where
+		T: Send + 'static,
+		F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+	{
+		let (sender, receiver) = oneshot::channel();
+		let job: Job = Box::new(move |connection| {
+			let _ = sender.send(catch_unwind(AssertUnwindSafe(|| f(connection))));
+		});
+		self.jobs.send(job).map_err(|_| Error::Disconnected)?;
+		match receiver.await {
+			Ok(result) => result.unwrap_or_else(|panic| resume_unwind(panic)),
+			Err(_) => Err(Error::Disconnected),
+		}
+	}
+
+	/// Gets a table in the database using its name, creating one if it doesn't exist.
+	pub async fn table<T: Into<String>>(&self, table: T) -> Result<Table<i64>> {
+		let table = table.into();
+		self.call(move |connection| connection.table(table)).await
+	}
+}
+
+impl<I: FromSql + Send + 'static> Table<I> {
+	/// Inserts a JSON object into the table without blocking.
+	///
+	/// See [`insert`] for more details.
+	///
+	/// [`insert`]: #method.insert
+	pub async fn insert_async<T: Serialize>(&self, data: T, connection: &AsyncConnection) -> Result<I> {
+		let (table, data) = (self.clone(), serde_json::to_value(data)?);
+		connection.call(move |connection| table.insert(data, connection)).await
+	}
+
+	/// Inserts many JSON objects inside of a single transaction without blocking.
+	///
+	/// See [`insert_many`] for more details.
+	///
+	/// [`insert_many`]: #method.insert_many
+	pub async fn insert_many_async<D, T>(&self, data: D, connection: &AsyncConnection) -> Result<Vec<I>>
+	where
+		D: IntoIterator<Item=T>,
+		T: Serialize,
+	{
+		let table = self.clone();
+		let data = data.into_iter().map(serde_json::to_value).collect::<serde_json::Result<Vec<_>>>()?;
+		connection.call(move |connection| table.insert_many(data, connection)).await
+	}
+}
+impl<I: FromSql + ToSql + Send + 'static> Table<I> {
+	/// Deletes an entry with the given primary key without blocking.
+	pub async fn delete_async(&self, id: I, connection: &AsyncConnection) -> Result<()> {
+		let table = self.clone();
+		connection.call(move |connection| table.delete(id, connection)).await
+	}
+}
+
+impl<I: FromSql + ToSql + Send + 'static> Operation<'_, I> {
+	/// Gets only the JSON object without blocking.
+	///
+	/// See [`data`] for more details.
+	///
+	/// [`data`]: #method.data
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{AsyncConnection, json};
+	/// # #[tokio::main(flavor = "current_thread")]
+	/// # async fn main() -> nosqlite::Result<()> {
+	/// # let connection = AsyncConnection::in_memory()?;
+	/// # let table = connection.table("people").await?;
+	/// let id = table.insert_async(json!({"name": "Hiruna"}), &connection).await?;
+	/// table.get(id).set_async("age", 19, &connection).await?;
+	/// let person: serde_json::Value = table.get(id).data_async(&connection).await?.unwrap();
+	/// assert_eq!(person, json!({"name": "Hiruna", "age": 19}));
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub async fn data_async<T>(self, connection: &AsyncConnection) -> Result<Option<T>>
+	where
+		T: DeserializeOwned + Send + 'static,
+	{
+		connection.call(self.detach(|operation, connection| operation.data(connection))).await
+	}
+
+	/// Gets both the id and the JSON object without blocking.
+	pub async fn entry_async<T>(self, connection: &AsyncConnection) -> Result<Option<Entry<I, T>>>
+	where
+		T: DeserializeOwned + Send + 'static,
+	{
+		connection.call(self.detach(|operation, connection| operation.entry(connection))).await
+	}
+
+	/// Sets the value of a field without blocking and returns whether the entry exists.
+	///
+	/// See [`set`] for more details.
+	///
+	/// [`set`]: #method.set
+	pub async fn set_async<T>(self, field: &str, value: T, connection: &AsyncConnection) -> Result<bool>
+	where
+		T: ToSql + Send + 'static,
+	{
+		let field = field.to_owned();
+		connection.call(self.detach(move |operation, connection| operation.set(&field, value, connection))).await
+	}
+
+	/// Removes a field without blocking and returns whether the entry exists.
+	pub async fn remove_async(self, field: &str, connection: &AsyncConnection) -> Result<bool> {
+		let field = field.to_owned();
+		connection.call(self.detach(move |operation, connection| operation.remove(&field, connection))).await
+	}
+
+	/// Patches the JSON object without blocking and returns whether the entry exists.
+	///
+	/// See [`patch`] for more details.
+	///
+	/// [`patch`]: #method.patch
+	pub async fn patch_async<T: Serialize>(self, value: T, connection: &AsyncConnection) -> Result<bool> {
+		let value = serde_json::to_value(value)?;
+		connection.call(self.detach(move |operation, connection| operation.patch(value, connection))).await
+	}
+
+	/// Copies the operation so that it can be used on the connection's thread.
+	fn detach<T, F>(self, f: F) -> impl FnOnce(&Connection) -> Result<T> + Send + 'static
+	where
+		F: FnOnce(&Operation<'_, I>, &Connection) -> Result<T> + Send + 'static,
+	{
+		let (data_key, id_key, table, id) = (self.data_key.to_owned(), self.id_key.to_owned(), self.table.to_owned(), self.id);
+		move |connection| f(&Operation { data_key: &data_key, id, id_key: &id_key, table: &table }, connection)
+	}
+}
+
+impl<I: FromSql + Send + 'static, W: Filter, S: Sort> Iterator<'_, I, W, S> {
+	/// ***GET***s only the JSON objects without blocking.
+	///
+	/// See [`data`] for more details.
+	///
+	/// [`data`]: #method.data
+	pub async fn data_async<T>(&self, connection: &AsyncConnection) -> Result<Vec<T>>
+	where
+		T: DeserializeOwned + Send + 'static,
+	{
+		connection.call(self.detach(|iterator, connection| iterator.data(connection))?).await
+	}
+
+	/// ***GET***s both the ids and the JSON objects without blocking.
+	pub async fn entry_async<T>(&self, connection: &AsyncConnection) -> Result<Vec<Entry<I, T>>>
+	where
+		T: DeserializeOwned + Send + 'static,
+	{
+		connection.call(self.detach(|iterator, connection| iterator.entry(connection))?).await
+	}
+
+	/// ***GET***s only the ids without blocking.
+	pub async fn id_async(&self, connection: &AsyncConnection) -> Result<Vec<I>> {
+		connection.call(self.detach(|iterator, connection| iterator.id(connection))?).await
+	}
+
+	/// ***GET***s a field of each JSON object without blocking.
+	///
+	/// See [`field`] for more details.
+	///
+	/// [`field`]: #method.field
+	pub async fn field_async<T>(&self, field: &str, connection: &AsyncConnection) -> Result<Vec<T>>
+	where
+		T: FromSql + Send + 'static,
+	{
+		let field = field.to_owned();
+		connection.call(self.detach(move |iterator, connection| iterator.field(&field, connection))?).await
+	}
+
+	/// Counts the entries without blocking.
+	pub async fn count_async(&self, connection: &AsyncConnection) -> Result<usize> {
+		connection.call(self.detach(|iterator, connection| iterator.count(connection))?).await
+	}
+
+	/// Deletes the entries without blocking.
+	pub async fn delete_async(&self, connection: &AsyncConnection) -> Result<()> {
+		connection.call(self.detach(|iterator, connection| iterator.delete(connection))?).await
+	}
+
+	/// Reads the JSON objects one at a time without blocking.
+	///
+	/// The entries are read a page at a time, and each page is read by its own job on the connection's thread,
+	/// so other operations can use the connection while the stream is being read.
+	/// Entries that are changed in between pages may be skipped or read twice,
+	/// so use [`AsyncConnection::call`] to read them inside of a transaction if that matters.
+	/// The entries are sorted by their id after any other sorts, so that every page continues from the last one.
+	///
+	/// [`AsyncConnection::call`]: struct.AsyncConnection.html#method.call
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{AsyncConnection, field, json, Key};
+	/// # use futures_core::Stream;
+	/// # use std::{future::poll_fn, pin::Pin};
+	/// # #[tokio::main(flavor = "current_thread")]
+	/// # async fn main() -> nosqlite::Result<()> {
+	/// # let connection = AsyncConnection::in_memory()?;
+	/// # let table = connection.table("test").await?;
+	/// table.insert_many_async((0..1000).map(|number| json!({"number": number})), &connection).await?;
+	/// let mut stream = table.iter().filter(field("number").gte(900)).stream_async::<serde_json::Value>(&connection)?;
+	/// let mut total = 0;
+	/// while let Some(object) = poll_fn(|context| Pin::new(&mut stream).poll_next(context)).await {
+	/// 	total += object?["number"].as_i64().unwrap();
+	/// 	// The connection isn't held up by the stream
+	/// 	if total == 900 {
+	/// 		assert_eq!(table.iter().count_async(&connection).await?, 1000);
+	/// 	}
+	/// }
+	/// assert_eq!(total, (900..1000).sum::<i64>());
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn stream_async<T>(&self, connection: &AsyncConnection) -> Result<AsyncStream<T>>
+	where
+		T: DeserializeOwned + Send + 'static,
+	{
+		self.stream_with(connection, |iterator, connection| iterator.try_data(connection))
+	}
+
+	/// Reads the ids and the JSON objects one at a time without blocking.
+	///
+	/// See [`stream_async`] for more details.
+	///
+	/// [`stream_async`]: #method.stream_async
+	pub fn stream_entry_async<T>(&self, connection: &AsyncConnection) -> Result<AsyncStream<Entry<I, T>>>
+	where
+		T: DeserializeOwned + Send + 'static,
+	{
+		self.stream_with(connection, |iterator, connection| iterator.try_entry(connection))
+	}
+
+	/// Creates a stream that reads each page of entries using `read`.
+	fn stream_with<T, F>(&self, connection: &AsyncConnection, read: F) -> Result<AsyncStream<T>>
+	where
+		F: Fn(&Iterator<'_, I, &Rendered, &Rendered>, &Connection) -> Result<Vec<Result<T>>> + Send + Sync + 'static,
+	{
+		let mut detached = self.detached()?;
+		// The pages can only carry on from each other if the entries are always in the same order
+		detached.rendered.order_by.push(detached.id_key.clone());
+		let offset = detached.offset.unwrap_or(0);
+		let remaining = detached.limit;
+		let read_page = move |connection: &Connection, offset, limit| {
+			read(&Iterator { offset: Some(offset), limit: Some(limit), ..detached.iterator() }, connection)
+		};
+		Ok(AsyncStream {
+			connection: connection.clone(),
+			read_page: Arc::new(read_page),
+			offset,
+			remaining,
+			entries: VecDeque::new(),
+			reading: None,
+			finished: false,
+		})
+	}
+
+	/// Renders the query so that it can be used on the connection's thread.
+	fn detach<T, F>(&self, f: F) -> Result<impl FnOnce(&Connection) -> Result<T> + Send + 'static>
+	where
+		F: FnOnce(&Iterator<'_, I, &Rendered, &Rendered>, &Connection) -> Result<T> + Send + 'static,
+	{
+		let detached = self.detached()?;
+		Ok(move |connection: &Connection| f(&detached.iterator(), connection))
+	}
+
+	/// Renders the query and copies the rest of the iterator so that it can be sent to the connection's thread.
+	fn detached(&self) -> Result<Detached<I>> {
+		let query = self.query()?;
+		Ok(Detached {
+			data_key: self.data_key.to_owned(),
+			id_key: self.id_key.to_owned(),
+			table_key: self.table_key.to_owned(),
+			table_name: self.table_name.to_owned(),
+			limit: query.limit,
+			offset: query.offset,
+			rendered: Rendered { where_: query.where_, order_by: query.order_by },
+			id_type: self.id_type,
+		})
+	}
+}
+
+/// An iterator whose query has already been rendered, which can be sent to the connection's thread.
+struct Detached<I> {
+	data_key: String,
+	id_key: String,
+	table_key: String,
+	table_name: String,
+	limit: Option<u32>,
+	offset: Option<u32>,
+	rendered: Rendered,
+	id_type: PhantomData<fn() -> I>,
+}
+impl<I> Detached<I> {
+	/// Borrows the iterator back.
+	fn iterator(&self) -> Iterator<'_, I, &Rendered, &Rendered> {
+		Iterator {
+			data_key: &self.data_key,
+			id_key: &self.id_key,
+			id_type: self.id_type,
+			limit: self.limit,
+			offset: self.offset,
+			order_by: &self.rendered,
+			where_: &self.rendered,
+			table_key: &self.table_key,
+			table_name: &self.table_name,
+			generated: Arc::default(),
+		}
+	}
+}
+
+/// The clauses of a query that have already been rendered.
+struct Rendered {
+	where_: Option<Clause>,
+	order_by: Vec<String>,
+}
+impl Filter for &Rendered {
+	fn where_(&self, _: &str) -> Result<Option<Clause>> { Ok(self.where_.clone()) }
+}
+impl Sort for &Rendered {
+	fn order_by(&self, _: &str) -> Vec<String> { self.order_by.clone() }
+}
+
+/// The entries being read from the database without blocking.
+///
+/// Create this using [`Iterator::stream_async`].
+/// This implements `futures_core::Stream`, with a separate result for each entry.
+/// If reading a page of entries panics, the panic is resumed in the task polling the stream
+/// after the entries of the pages before it have been received.
+///
+/// [`Iterator::stream_async`]: struct.Iterator.html#method.stream_async
+///
+/// # Example
+///
+/// ```
+/// # use nosqlite::{AsyncConnection, json};
+/// # use futures_core::Stream;
+/// # use serde::{Deserialize, Deserializer};
+/// # use std::{future::poll_fn, pin::Pin};
+/// struct Panics;
+/// impl<'de> Deserialize<'de> for Panics {
+/// 	fn deserialize<D: Deserializer<'de>>(_: D) -> Result<Self, D::Error> { panic!("can't be deserialised") }
+/// }
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> nosqlite::Result<()> {
+/// # let connection = AsyncConnection::in_memory()?;
+/// # let table = connection.table("test").await?;
+/// table.insert_async(json!(1), &connection).await?;
+/// let mut stream = table.iter().stream_async::<Panics>(&connection)?;
+/// let task = tokio::spawn(async move { poll_fn(|context| Pin::new(&mut stream).poll_next(context)).await.is_some() });
+/// assert!(task.await.unwrap_err().is_panic());
+/// // The connection can still be used
+/// assert_eq!(table.iter().count_async(&connection).await?, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncStream<T> {
+	connection: AsyncConnection,
+	read_page: Arc<ReadPage<T>>,
+	/// How many entries to skip before the next page.
+	offset: u32,
+	/// How many more entries can be read, if there is a limit.
+	remaining: Option<u32>,
+	/// The entries that have been read but not used yet.
+	entries: VecDeque<Result<T>>,
+	/// The page that is being read.
+	reading: Option<Page<T>>,
+	/// Whether the last page has been read.
+	finished: bool,
+}
+impl<T: Send + 'static> AsyncStream<T> {
+	/// Starts reading the next page on the connection's thread.
+	fn read_next_page(&self) -> Page<T> {
+		let limit = self.remaining.map_or(STREAM_PAGE, |remaining| remaining.min(STREAM_PAGE));
+		let (connection, read_page, offset) = (self.connection.clone(), self.read_page.clone(), self.offset);
+		Box::pin(async move { connection.call(move |connection| read_page(connection, offset, limit)).await })
+	}
+}
+// None of the fields are pinned
+impl<T> Unpin for AsyncStream<T> {}
+impl<T: Send + 'static> FuturesStream for AsyncStream<T> {
+	type Item = Result<T>;
+	fn poll_next(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Option<Result<T>>> {
+		loop {
+			if let Some(entry) = self.entries.pop_front() {
+				return Poll::Ready(Some(entry));
+			}
+			if self.finished || self.remaining == Some(0) {
+				return Poll::Ready(None);
+			}
+			let page = match self.reading.as_mut() {
+				Some(reading) => match reading.as_mut().poll(context) {
+					Poll::Ready(page) => page,
+					Poll::Pending => return Poll::Pending,
+				},
+				None => {
+					self.reading = Some(self.read_next_page());
+					continue;
+				},
+			};
+			self.reading = None;
+			match page {
+				Ok(page) => {
+					let read = page.len() as u32;
+					self.finished = read < self.remaining.map_or(STREAM_PAGE, |remaining| remaining.min(STREAM_PAGE));
+					self.offset += read;
+					self.remaining = self.remaining.map(|remaining| remaining - read);
+					self.entries.extend(page);
+				},
+				// An error before the first entry of a page is returned in place of the entries
+				Err(error) => {
+					self.finished = true;
+					return Poll::Ready(Some(Err(error)));
+				},
+			}
+		}
+	}
+}
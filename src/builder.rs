@@ -0,0 +1,187 @@
+use rusqlite::{Connection as SqliteConnection, OpenFlags};
+
+use std::{fmt::Display, path::Path, time::Duration};
+
+use crate::{Connection, quote, quote_literal, Result};
+
+/// Options for opening a [`Connection`], such as SQLite's pragmas and whether it is read-only.
+///
+/// Create this using [`Connection::builder`].
+/// The pragmas are set in the order they were given, every time a connection is opened.
+///
+/// [`Connection`]: struct.Connection.html
+/// [`Connection::builder`]: struct.Connection.html#method.builder
+///
+/// # Example
+///
+/// ```
+/// # use nosqlite::{Connection, JournalMode, Synchronous};
+/// # use std::time::Duration;
+/// # let path = std::env::temp_dir().join("nosqlite_builder.db");
+/// let connection = Connection::builder()
+/// 	.journal_mode(JournalMode::Wal)
+/// 	.synchronous(Synchronous::Normal)
+/// 	.busy_timeout(Duration::from_secs(5))
+/// 	.foreign_keys(true)
+/// 	.open(&path)?;
+/// let mode: String = connection.as_ref().query_row("PRAGMA journal_mode", rusqlite::NO_PARAMS, |row| row.get(0))?;
+/// assert_eq!(mode, "wal");
+/// # drop(connection);
+/// # std::fs::remove_file(&path).ok();
+/// # nosqlite::Result::Ok(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionBuilder {
+	pragmas: Vec<(String, String)>,
+	read_only: bool,
+}
+impl ConnectionBuilder {
+	/// Sets how SQLite keeps track of changes that haven't been written to the database yet.
+	pub fn journal_mode(self, mode: JournalMode) -> Self { self.pragma("journal_mode", mode.as_str()) }
+
+	/// Sets how often SQLite waits for changes to be written to the disk.
+	pub fn synchronous(self, synchronous: Synchronous) -> Self { self.pragma("synchronous", synchronous.as_str()) }
+
+	/// Sets how long to wait for another connection to unlock the database before returning an error.
+	pub fn busy_timeout(self, timeout: Duration) -> Self { self.pragma("busy_timeout", timeout.as_millis()) }
+
+	/// Sets the number of pages of the database kept in memory,
+	/// or the number of kibibytes if it is negative.
+	pub fn cache_size(self, size: i64) -> Self { self.pragma("cache_size", size) }
+
+	/// Sets whether foreign key constraints are enforced.
+	pub fn foreign_keys(self, enabled: bool) -> Self { self.pragma("foreign_keys", enabled as u8) }
+
+	/// Sets the maximum number of bytes of the database that are memory-mapped.
+	pub fn mmap_size(self, size: u64) -> Self { self.pragma("mmap_size", size) }
+
+	/// Sets any pragma when the connection is opened.
+	///
+	/// Both the name and the value are quoted so they can't be used to run other SQL statements.
+	pub fn pragma<N: Into<String>, V: Display>(mut self, name: N, value: V) -> Self {
+		self.pragmas.push((name.into(), value.to_string()));
+		self
+	}
+
+	/// Sets whether the connection can only read from the database.
+	///
+	/// Trying to change a read-only database returns an error,
+	/// and opening a database that doesn't exist fails instead of creating it.
+	pub fn read_only(mut self, read_only: bool) -> Self {
+		self.read_only = read_only;
+		self
+	}
+
+	/// Opens a connection to a sqlite database.
+	pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<Connection> {
+		self.configure(SqliteConnection::open_with_flags(path, self.flags())?)
+	}
+
+	/// Opens a new connection to a sqlite database in-memory.
+	pub fn in_memory(&self) -> Result<Connection> {
+		self.configure(SqliteConnection::open_in_memory_with_flags(self.flags())?)
+	}
+
+	/// Opens a connection to an in-memory database that is shared with every other connection using the same name.
+	///
+	/// The database is deleted once the last connection to it is closed.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json};
+	/// let first = Connection::builder().shared_memory("test")?;
+	/// let second = Connection::builder().shared_memory("test")?;
+	/// first.table("people")?.insert(json!({"name": "Hiruna"}), &first)?;
+	/// assert_eq!(second.table("people")?.iter().count(&second)?, 1);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn shared_memory(&self, name: &str) -> Result<Connection> {
+		// The name is part of a URI so the characters that have a meaning in one are escaped
+		let name = name.chars().fold(String::new(), |mut escaped, c| {
+			match c {
+				'%' | '?' | '#' | '&' | '=' | '/' => escaped.push_str(&format!("%{:02X}", c as u32)),
+				c => escaped.push(c),
+			}
+			escaped
+		});
+		self.open(format!("file:{}?mode=memory&cache=shared", name))
+	}
+
+	/// The flags used to open the connection.
+	fn flags(&self) -> OpenFlags {
+		let access = if self.read_only {
+			OpenFlags::SQLITE_OPEN_READ_ONLY
+		} else {
+			OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+		};
+		access | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI
+	}
+
+	/// Sets the pragmas on the connection.
+	fn configure(&self, connection: SqliteConnection) -> Result<Connection> {
+		for (name, value) in &self.pragmas {
+			// Some pragmas return their new value so `execute` can't be used
+			connection.execute_batch(&format!("PRAGMA {} = {}", quote(name), quote_literal(value)))?;
+		}
+		Ok(Connection::new(connection))
+	}
+}
+
+/// How SQLite keeps track of changes that haven't been written to the database yet.
+///
+/// See [SQLite's documentation](https://www.sqlite.org/pragma.html#pragma_journal_mode) for more details.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum JournalMode {
+	/// A rollback journal that is deleted after each transaction, which is the default.
+	Delete,
+	/// A rollback journal that is emptied after each transaction.
+	Truncate,
+	/// A rollback journal whose header is overwritten after each transaction.
+	Persist,
+	/// A rollback journal that is kept in memory.
+	Memory,
+	/// A write-ahead log, which lets reading happen at the same time as writing.
+	Wal,
+	/// No journal, so transactions can't be rolled back safely.
+	Off,
+}
+impl JournalMode {
+	/// The name of the mode used by SQLite.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			JournalMode::Delete => "DELETE",
+			JournalMode::Truncate => "TRUNCATE",
+			JournalMode::Persist => "PERSIST",
+			JournalMode::Memory => "MEMORY",
+			JournalMode::Wal => "WAL",
+			JournalMode::Off => "OFF",
+		}
+	}
+}
+
+/// How often SQLite waits for changes to be written to the disk.
+///
+/// See [SQLite's documentation](https://www.sqlite.org/pragma.html#pragma_synchronous) for more details.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Synchronous {
+	/// Never waits.
+	Off,
+	/// Waits at the most important moments, which is safe when using a write-ahead log.
+	Normal,
+	/// Waits after every transaction, which is the default.
+	Full,
+	/// Also waits after the journal is deleted.
+	Extra,
+}
+impl Synchronous {
+	/// The name of the level used by SQLite.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Synchronous::Off => "OFF",
+			Synchronous::Normal => "NORMAL",
+			Synchronous::Full => "FULL",
+			Synchronous::Extra => "EXTRA",
+		}
+	}
+}
@@ -0,0 +1,132 @@
+use rusqlite::{Action, types::{FromSql, FromSqlError, FromSqlResult, ValueRef}};
+
+use std::{collections::HashSet, sync::{Arc, mpsc::{channel, Receiver, Sender}, Mutex}};
+
+use crate::{Connection, lock};
+
+/// A change to an entry in a table, delivered after the change has been committed.
+///
+/// Create a receiver for these using [`Connection::subscribe`] or [`Table::watch`].
+///
+/// [`Connection::subscribe`]: struct.Connection.html#method.subscribe
+/// [`Table::watch`]: struct.Table.html#method.watch
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ChangeEvent {
+	/// The name of the table that was changed.
+	pub table: String,
+	/// Whether the entry was inserted, updated or deleted.
+	pub op: ChangeOp,
+	/// The rowid of the entry, which is the same as its id in a table created by [`Connection::table`].
+	///
+	/// [`Connection::table`]: struct.Connection.html#method.table
+	pub id: i64,
+}
+
+/// The ways that an entry can be changed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ChangeOp {
+	/// The entry was inserted.
+	Insert,
+	/// The entry was updated.
+	Update,
+	/// The entry was deleted.
+	Delete,
+}
+impl ChangeOp {
+	/// The name of the change as it is stored in a changelog.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			ChangeOp::Insert => "insert",
+			ChangeOp::Update => "update",
+			ChangeOp::Delete => "delete",
+		}
+	}
+}
+impl FromSql for ChangeOp {
+	fn column_result(value: ValueRef) -> FromSqlResult<Self> {
+		match value.as_str()? {
+			"insert" => Ok(ChangeOp::Insert),
+			"update" => Ok(ChangeOp::Update),
+			"delete" => Ok(ChangeOp::Delete),
+			_ => Err(FromSqlError::InvalidType),
+		}
+	}
+}
+
+/// A change to an entry that was recorded in a table's changelog.
+///
+/// Read these using [`Table::changes_since`].
+///
+/// [`Table::changes_since`]: struct.Table.html#method.changes_since
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Change<I> {
+	/// The position of the change in the changelog, which is larger than that of every change before it.
+	pub seq: i64,
+	/// Whether the entry was inserted, updated or deleted.
+	pub op: ChangeOp,
+	/// The id of the entry.
+	pub id: I,
+}
+
+/// The changes that haven't been committed yet and the receivers they are sent to once they are.
+///
+/// This is owned by SQLite's hooks, and by the connection so that more receivers can be added.
+#[derive(Default)]
+pub(crate) struct Changes {
+	pending: Vec<ChangeEvent>,
+	/// The tables that belong to other tables, whose changes aren't sent.
+	internal: HashSet<String>,
+	/// The table that each receiver is watching, or `None` if it is watching every table.
+	subscribers: Vec<(Option<String>, Sender<ChangeEvent>)>,
+}
+impl Changes {
+	/// Adds a receiver for the changes to a table, or every table if it is `None`.
+	///
+	/// SQLite's hooks are set the first time this is used on a connection.
+	pub(crate) fn subscribe(connection: &Connection, table: Option<String>, internal: HashSet<String>) -> Receiver<ChangeEvent> {
+		let (sender, receiver) = channel();
+		let changes = connection.changes.borrow_mut().get_or_insert_with(|| Self::hook(connection)).clone();
+		let mut changes = lock(&changes);
+		changes.internal.extend(internal);
+		changes.subscribers.push((table, sender));
+		receiver
+	}
+
+	/// Sets the hooks of a connection so that its changes are sent once they are committed.
+	fn hook(connection: &Connection) -> Arc<Mutex<Changes>> {
+		let changes = Arc::new(Mutex::new(Changes::default()));
+		let hook = changes.clone();
+		connection.connection.update_hook(Some(move |action, _: &str, table: &str, id| {
+			let op = match action {
+				Action::SQLITE_INSERT => ChangeOp::Insert,
+				Action::SQLITE_UPDATE => ChangeOp::Update,
+				Action::SQLITE_DELETE => ChangeOp::Delete,
+				_ => return,
+			};
+			lock(&hook).pending.push(ChangeEvent { table: table.into(), op, id });
+		}));
+		let hook = changes.clone();
+		connection.connection.commit_hook(Some(move || {
+			lock(&hook).deliver();
+			// Returning true would turn the commit into a rollback
+			false
+		}));
+		let hook = changes.clone();
+		connection.connection.rollback_hook(Some(move || lock(&hook).pending.clear()));
+		changes
+	}
+
+	/// Sends the pending changes to the receivers that are watching their table,
+	/// removing any receivers that have been dropped.
+	///
+	/// Changes to the internal tables are skipped.
+	fn deliver(&mut self) {
+		let Self { pending, internal, subscribers } = self;
+		for event in pending.drain(..).filter(|event| !internal.contains(&event.table)) {
+			subscribers.retain(|(table, sender)| match table {
+				Some(table) if *table != event.table => true,
+				_ => sender.send(event.clone()).is_ok(),
+			});
+		}
+	}
+}
@@ -0,0 +1,170 @@
+use rusqlite::{Connection as SqliteConnection, types::{FromSql, ToSql}};
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::marker::PhantomData;
+
+use crate::{Entry, Filter, Iterator, Result, Sort, Table};
+
+/// A table that only stores JSON objects of one type.
+///
+/// Create this using [`Connection::collection`].
+/// The type doesn't need to be given when reading entries and only that type can be inserted.
+/// To use the methods in [`Table`], call `as_ref` on the `Collection`.
+///
+/// [`Connection::collection`]: struct.Connection.html#method.collection
+/// [`Table`]: struct.Table.html
+///
+/// # Example
+///
+/// ```
+/// # use nosqlite::{Collection, Connection, field, Key};
+/// # use serde::{Deserialize, Serialize};
+/// # let connection = Connection::in_memory()?;
+/// #[derive(Debug, Deserialize, PartialEq, Serialize)]
+/// struct Person {
+/// 	name: String,
+/// 	age: u8,
+/// }
+/// let people: Collection<Person> = connection.collection("people")?;
+/// let id = people.insert(&Person { name: "Hiruna".into(), age: 19 }, &connection)?;
+/// people.insert(&Person { name: "Bob".into(), age: 13 }, &connection)?;
+/// assert_eq!(people.get(id, &connection)?.unwrap().name, "Hiruna");
+/// let adults = people.iter().filter(field("age").gte(18)).data(&connection)?;
+/// assert_eq!(adults, [Person { name: "Hiruna".into(), age: 19 }]);
+/// # nosqlite::Result::Ok(())
+/// ```
+pub struct Collection<T, I = i64> {
+	table: Table<I>,
+	data_type: PhantomData<fn() -> T>,
+}
+impl<T, I> Collection<T, I> {
+	/// Uses a table to store JSON objects of one type.
+	///
+	/// Generally using [`Connection::collection`] is recommended instead of this.
+	///
+	/// [`Connection::collection`]: struct.Connection.html#method.collection
+	pub fn new(table: Table<I>) -> Self {
+		Self { table, data_type: PhantomData }
+	}
+}
+impl<T: Serialize, I: FromSql> Collection<T, I> {
+	/// Inserts a JSON object and returns its id.
+	pub fn insert<C: AsRef<SqliteConnection>>(&self, data: &T, connection: C) -> Result<I> {
+		self.table.insert(data, connection)
+	}
+
+	/// Inserts many JSON objects inside of a single transaction and returns their ids.
+	///
+	/// See [`Table::insert_many`] for more details.
+	///
+	/// [`Table::insert_many`]: struct.Table.html#method.insert_many
+	pub fn insert_many<'d, D, C>(&self, data: D, connection: C) -> Result<Vec<I>>
+	where
+		D: IntoIterator<Item=&'d T>,
+		T: 'd,
+		C: AsRef<SqliteConnection>,
+	{
+		self.table.insert_many(data, connection)
+	}
+}
+impl<T: DeserializeOwned, I: FromSql> Collection<T, I> {
+	/// Iterate through all the entries in the collection.
+	pub fn iter(&self) -> TypedIterator<'_, T, I, (), ()> {
+		TypedIterator { iterator: self.table.iter(), data_type: PhantomData }
+	}
+}
+impl<T: DeserializeOwned, I: FromSql + ToSql> Collection<T, I> {
+	/// Gets a JSON object using its id.
+	pub fn get<C: AsRef<SqliteConnection>>(&self, id: I, connection: C) -> Result<Option<T>> {
+		self.table.get(id).data(connection)
+	}
+
+	/// Deletes an entry with the given id.
+	pub fn delete<C: AsRef<SqliteConnection>>(&self, id: I, connection: C) -> Result<()> {
+		self.table.delete(id, connection)
+	}
+}
+impl<T, I> AsRef<Table<I>> for Collection<T, I> {
+	fn as_ref(&self) -> &Table<I> { &self.table }
+}
+
+/// An [`Iterator`] through a [`Collection`] that knows the type of its JSON objects.
+///
+/// To use the methods in [`Iterator`] that don't deserialise JSON objects,
+/// call `as_ref` on the `TypedIterator`.
+///
+/// [`Collection`]: struct.Collection.html
+/// [`Iterator`]: struct.Iterator.html
+#[must_use = "This struct does not do anything until executed"]
+pub struct TypedIterator<'a, T, I, W, S> {
+	iterator: Iterator<'a, I, W, S>,
+	data_type: PhantomData<fn() -> T>,
+}
+impl<'a, T: DeserializeOwned, I: FromSql, W: Filter, S: Sort> TypedIterator<'a, T, I, W, S> {
+	/// ***GET***s the JSON objects.
+	///
+	/// See [`Iterator::data`] for more details.
+	///
+	/// [`Iterator::data`]: struct.Iterator.html#method.data
+	pub fn data<C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<Vec<T>> {
+		self.iterator.data(connection)
+	}
+
+	/// ***GET***s the JSON objects, with a separate result for each entry.
+	///
+	/// See [`Iterator::try_data`] for more details.
+	///
+	/// [`Iterator::try_data`]: struct.Iterator.html#method.try_data
+	pub fn try_data<C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<Vec<Result<T>>> {
+		self.iterator.try_data(connection)
+	}
+
+	/// ***GET***s both the ids and the JSON objects.
+	///
+	/// See [`Iterator::entry`] for more details.
+	///
+	/// [`Iterator::entry`]: struct.Iterator.html#method.entry
+	pub fn entry<C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<Vec<Entry<I, T>>> {
+		self.iterator.entry(connection)
+	}
+
+	/// ***GET***s both the ids and the JSON objects, with a separate result for each entry.
+	///
+	/// See [`Iterator::try_entry`] for more details.
+	///
+	/// [`Iterator::try_entry`]: struct.Iterator.html#method.try_entry
+	pub fn try_entry<C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<Vec<Result<Entry<I, T>>>> {
+		self.iterator.try_entry(connection)
+	}
+
+	/// Only use the entries that meet the condition.
+	///
+	/// See [`Iterator::filter`] for more details.
+	///
+	/// [`Iterator::filter`]: struct.Iterator.html#method.filter
+	pub fn filter<A: Filter>(self, filter: A) -> TypedIterator<'a, T, I, A, S> {
+		TypedIterator { iterator: self.iterator.filter(filter), data_type: PhantomData }
+	}
+
+	/// Sort by using a specific field(s).
+	///
+	/// See [`Iterator::sort`] for more details.
+	///
+	/// [`Iterator::sort`]: struct.Iterator.html#method.sort
+	pub fn sort<A: Sort>(self, key: A) -> TypedIterator<'a, T, I, W, A> {
+		TypedIterator { iterator: self.iterator.sort(key), data_type: PhantomData }
+	}
+
+	/// Skip over `n` entries.
+	pub fn skip(self, n: u32) -> Self {
+		TypedIterator { iterator: self.iterator.skip(n), data_type: PhantomData }
+	}
+
+	/// Only use the first `n` entries.
+	pub fn take(self, n: u32) -> Self {
+		TypedIterator { iterator: self.iterator.take(n), data_type: PhantomData }
+	}
+}
+impl<'a, T, I, W, S> AsRef<Iterator<'a, I, W, S>> for TypedIterator<'a, T, I, W, S> {
+	fn as_ref(&self) -> &Iterator<'a, I, W, S> { &self.iterator }
+}
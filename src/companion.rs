@@ -0,0 +1,207 @@
+//! The tables that are kept up to date alongside a table, such as its full-text search index or changelog.
+
+use rusqlite::{Connection as SqliteConnection, NO_PARAMS};
+
+use std::collections::HashSet;
+
+use crate::{changelog, ChangeOp, Identifier, quote, quote_literal, Result, search_index, Table};
+
+/// The table that records which tables belong to another table, so that they aren't listed as tables of their own.
+pub(crate) const COMPANIONS: &str = "nosqlite_companions";
+
+/// The events that the triggers of a companion table are run after.
+const EVENTS: [&str; 3] = ["insert", "update", "delete"];
+
+/// What a companion table is used for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Kind {
+	/// A full-text search index created by `Table::search_index`.
+	Search,
+	/// A changelog created by `Table::changelog`.
+	Changelog,
+}
+impl Kind {
+	/// Every kind of companion table.
+	const ALL: [Kind; 2] = [Kind::Search, Kind::Changelog];
+
+	/// How this kind is written in the table of companions.
+	fn as_str(self) -> &'static str {
+		match self {
+			Kind::Search => "search",
+			Kind::Changelog => "changelog",
+		}
+	}
+
+	/// The name of this kind of companion for a table.
+	fn name(self, table: &str) -> String {
+		match self {
+			Kind::Search => search_index(table),
+			Kind::Changelog => changelog(table),
+		}
+	}
+
+	/// The endings of the names of the tables that SQLite uses to store this kind of companion.
+	fn shadow_tables(self) -> &'static [&'static str] {
+		match self {
+			Kind::Search => &["_data", "_idx", "_content", "_docsize", "_config"],
+			Kind::Changelog => &[],
+		}
+	}
+
+	/// Creates the triggers that keep a companion of this kind up to date, using the columns it already has.
+	fn triggers(self, connection: &SqliteConnection, table: &Identifier, id: &Identifier, data: &Identifier, companion: &Identifier)
+		-> Result<String>
+	{
+		match self {
+			Kind::Search => {
+				// The columns of a full-text index are named after the paths of the fields they hold
+				let mut statement = connection.prepare("SELECT name FROM pragma_table_info(?)")?;
+				let paths = statement.query_map([companion.as_str()], |row| row.get(0))?
+					.collect::<rusqlite::Result<Vec<String>>>()?;
+				Ok(search_triggers(table, data, companion, &paths))
+			},
+			Kind::Changelog => Ok(changelog_triggers(table, id, companion)),
+		}
+	}
+}
+
+/// Records that a table belongs to another table.
+pub(crate) fn record<I>(connection: &SqliteConnection, kind: Kind, companion: &Identifier, table: &Table<I>) -> Result<()> {
+	connection.execute_batch(&format!(r#"
+		CREATE TABLE IF NOT EXISTS {} (
+			name TEXT PRIMARY KEY,
+			kind TEXT NOT NULL,
+			owner TEXT NOT NULL,
+			id TEXT NOT NULL,
+			data TEXT NOT NULL
+		)
+	"#, quote(COMPANIONS)))?;
+	connection.execute(
+		&format!("INSERT OR REPLACE INTO {} (name, kind, owner, id, data) VALUES (?, ?, ?, ?, ?)", quote(COMPANIONS)),
+		[companion.as_str(), kind.as_str(), table.name.as_str(), table.id.as_str(), table.data.as_str()],
+	)?;
+	Ok(())
+}
+
+/// Finds the tables that belong to the tables that exist, including the tables that SQLite uses to store them,
+/// along with the table that records them.
+pub(crate) fn internal_tables(connection: &SqliteConnection) -> Result<HashSet<String>> {
+	let mut internal = HashSet::new();
+	if !recorded(connection)? {
+		return Ok(internal);
+	}
+	internal.insert(COMPANIONS.to_owned());
+	let mut statement = connection.prepare(&format!(
+		"SELECT companions.name, companions.kind FROM {} AS companions \
+			JOIN sqlite_master AS owners ON owners.type = 'table' AND owners.name = companions.owner",
+		quote(COMPANIONS),
+	))?;
+	let companions = statement.query_map(NO_PARAMS, |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+	for companion in companions {
+		let (name, kind) = companion?;
+		if let Some(kind) = parse(&kind) {
+			internal.extend(kind.shadow_tables().iter().map(|suffix| format!("{}{}", name, suffix)));
+		}
+		internal.insert(name);
+	}
+	Ok(internal)
+}
+
+/// Moves the tables that belong to a table over to its new name, along with the triggers that keep them up to date.
+///
+/// The table itself must already have been renamed.
+pub(crate) fn rename(connection: &SqliteConnection, from: &Identifier, to: &Identifier) -> Result<()> {
+	if !recorded(connection)? {
+		return Ok(());
+	}
+	let mut statement = connection.prepare(&format!("SELECT name, kind, id, data FROM {} WHERE owner = ?", quote(COMPANIONS)))?;
+	let companions = statement.query_map([from.as_str()], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+		.collect::<rusqlite::Result<Vec<(String, String, String, String)>>>()?;
+	for (name, kind, id, data) in companions {
+		let kind = match parse(&kind) {
+			Some(kind) => kind,
+			None => continue,
+		};
+		let (id, data, renamed) = (Identifier::new(id)?, Identifier::new(data)?, Identifier::new(kind.name(to.as_str()))?);
+		let drop_triggers = EVENTS.iter()
+			.map(|event| format!("DROP TRIGGER IF EXISTS {};", trigger(&name, event)))
+			.collect::<String>();
+		// Renaming a virtual table returns rows so it can't use `execute`
+		connection.execute_batch(&format!("{} ALTER TABLE {} RENAME TO {};", drop_triggers, quote(&name), renamed))?;
+		connection.execute_batch(&kind.triggers(connection, to, &id, &data, &renamed)?)?;
+		connection.execute(
+			&format!("UPDATE {} SET name = ?, owner = ? WHERE name = ?", quote(COMPANIONS)),
+			[renamed.as_str(), to.as_str(), &name],
+		)?;
+	}
+	Ok(())
+}
+
+/// Drops the tables that belong to a table.
+pub(crate) fn remove(connection: &SqliteConnection, table: &Identifier) -> Result<()> {
+	if !recorded(connection)? {
+		return Ok(());
+	}
+	let mut statement = connection.prepare(&format!("SELECT name FROM {} WHERE owner = ?", quote(COMPANIONS)))?;
+	let companions = statement.query_map([table.as_str()], |row| row.get(0))?
+		.collect::<rusqlite::Result<Vec<String>>>()?;
+	for companion in companions {
+		connection.execute_batch(&format!("DROP TABLE IF EXISTS {}", quote(&companion)))?;
+	}
+	connection.execute(&format!("DELETE FROM {} WHERE owner = ?", quote(COMPANIONS)), [table.as_str()])?;
+	Ok(())
+}
+
+/// The values of the fields that a full-text index holds for a row, in the order of its columns.
+pub(crate) fn search_values(row: &str, data: &Identifier, paths: &[String]) -> String {
+	paths.iter()
+		.map(|path| format!("json_extract({}.{}, {})", row, data, quote_literal(path)))
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+/// Creates the triggers that keep a full-text index up to date with its table.
+pub(crate) fn search_triggers(table: &Identifier, data: &Identifier, index: &Identifier, paths: &[String]) -> String {
+	let columns = paths.iter().map(|path| quote(path)).collect::<Vec<_>>().join(", ");
+	let insert = format!("INSERT INTO {} (rowid, {}) VALUES (new.rowid, {});", index, columns, search_values("new", data, paths));
+	let delete = format!("DELETE FROM {} WHERE rowid = old.rowid;", index);
+	format!(r#"
+		CREATE TRIGGER IF NOT EXISTS {insert_trigger} AFTER INSERT ON {table} BEGIN {insert} END;
+		CREATE TRIGGER IF NOT EXISTS {delete_trigger} AFTER DELETE ON {table} BEGIN {delete} END;
+		CREATE TRIGGER IF NOT EXISTS {update_trigger} AFTER UPDATE ON {table} BEGIN {delete} {insert} END;
+	"#,
+		table = table,
+		insert_trigger = trigger(index.as_str(), "insert"),
+		delete_trigger = trigger(index.as_str(), "delete"),
+		update_trigger = trigger(index.as_str(), "update"),
+		insert = insert,
+		delete = delete,
+	)
+}
+
+/// Creates the triggers that record the changes to a table in its changelog.
+pub(crate) fn changelog_triggers(table: &Identifier, id: &Identifier, changelog: &Identifier) -> String {
+	let trigger = |op: ChangeOp, row: &str| format!(
+		"CREATE TRIGGER IF NOT EXISTS {trigger} AFTER {event} ON {table} \
+			BEGIN INSERT INTO {changelog} (op, id) VALUES ('{op}', {row}.{id}); END;",
+		trigger = trigger(changelog.as_str(), op.as_str()),
+		event = op.as_str().to_uppercase(),
+		table = table,
+		changelog = changelog,
+		op = op.as_str(),
+		row = row,
+		id = id,
+	);
+	[trigger(ChangeOp::Insert, "new"), trigger(ChangeOp::Update, "new"), trigger(ChangeOp::Delete, "old")].concat()
+}
+
+/// The quoted name of the trigger that keeps a companion table up to date after an event.
+fn trigger(companion: &str, event: &str) -> String { quote(&format!("{}_{}", companion, event)) }
+
+/// Gets a kind of companion from how it is written in the table of companions.
+fn parse(kind: &str) -> Option<Kind> { Kind::ALL.iter().copied().find(|known| known.as_str() == kind) }
+
+/// Checks whether any companion tables have been recorded.
+fn recorded(connection: &SqliteConnection) -> Result<bool> {
+	Ok(connection.query_row("SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?", [COMPANIONS], |row| row.get(0))?)
+}
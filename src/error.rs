@@ -0,0 +1,276 @@
+use rusqlite::{Connection as SqliteConnection, Error as SqliteError, ffi, types::{ToSqlOutput, Value}, ToSql};
+
+use std::{fmt::{Display, Formatter, Result as FmtResult}, sync::Arc};
+
+/// A specialised `Result` type for this crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The errors that can be returned by this crate.
+///
+/// # Example
+///
+/// ```
+/// # use nosqlite::{Connection, Error, json};
+/// # let connection = Connection::in_memory()?;
+/// # let table = connection.table("test")?;
+/// table.insert(json!({"x": 1}), &connection)?;
+/// table.insert(json!({"x": "one"}), &connection)?;
+/// match table.iter().field::<i64, _>("x", &connection) {
+/// 	Err(Error::Deserialization { id, path, .. }) => {
+/// 		assert_eq!(id, Some(2.into()));
+/// 		assert_eq!(path.as_deref(), Some("$.x"));
+/// 	},
+/// 	_ => panic!("Expected the second entry to fail"),
+/// }
+/// # nosqlite::Result::Ok(())
+/// ```
+///
+/// Values that can't be serialised into JSON return an error instead of panicking.
+///
+/// ```
+/// # use nosqlite::{Connection, Error, field, Key};
+/// # use std::collections::HashMap;
+/// # let connection = Connection::in_memory()?;
+/// # let table = connection.table("test")?;
+/// // JSON objects can only have strings as keys
+/// let mut map = HashMap::new();
+/// map.insert((1, 2), 3);
+/// let result = table.iter().filter(field("map").eq(map)).id(&connection);
+/// assert!(matches!(result, Err(Error::Serialization(_))));
+///
+/// connection.as_ref().execute_batch("DROP TABLE test")?;
+/// assert!(matches!(table.iter().id(&connection), Err(Error::MissingTable(_))));
+/// # nosqlite::Result::Ok(())
+/// ```
+#[derive(Debug)]
+pub enum Error {
+	/// An error returned by SQLite.
+	Sqlite(SqliteError),
+	/// A value could not be serialised into JSON.
+	///
+	/// The error is shared so that a value which failed to serialise can be used in more than one statement.
+	Serialization(Arc<serde_json::Error>),
+	/// A value from the database could not be deserialised into the type requested.
+	Deserialization {
+		/// The id of the entry that could not be deserialised, if it is known.
+		id: Option<Value>,
+		/// The JSON path of the value that could not be deserialised, if it is known.
+		path: Option<String>,
+		/// The reason why the value could not be deserialised.
+		error: Box<dyn std::error::Error + Send + Sync>,
+	},
+	/// The table does not exist in the database.
+	MissingTable(String),
+	/// The path to a field in a JSON object is not valid.
+	InvalidPath(String),
+	/// An entry could not be inserted or updated because another entry has the same value in a unique index.
+	UniqueViolation {
+		/// The name of the unique index, if it is known.
+		index: Option<String>,
+		/// The JSON paths or columns that must be unique.
+		fields: Vec<String>,
+	},
+	/// The name of a table, column or index is not valid.
+	InvalidIdentifier {
+		/// The name that is not valid.
+		name: String,
+		/// Why the name is not valid.
+		reason: &'static str,
+	},
+	/// A filter can't be used as the condition of a partial index.
+	InvalidIndexFilter(&'static str),
+	/// The thread of an async connection stopped before the operation finished.
+	Disconnected,
+	/// A table could not be rebuilt to add a column to it.
+	CannotRebuild {
+		/// The name of the table.
+		table: String,
+		/// Why the table could not be rebuilt.
+		reason: &'static str,
+	},
+}
+impl Error {
+	/// Attaches the id of the entry and the JSON path to a deserialisation error.
+	///
+	/// Any other error is returned as is.
+	pub(crate) fn at(self, id: Option<Value>, path: Option<&str>) -> Self {
+		match self {
+			Error::Deserialization { id: None, path: None, error } =>
+				Error::Deserialization { id, path: path.map(Into::into), error },
+			error => error,
+		}
+	}
+
+	/// Finds the fields of the unique index in a unique violation if they aren't known.
+	///
+	/// SQLite only gives the name of an index when the index is on an expression,
+	/// such as a field in a JSON object.
+	pub(crate) fn with_index_fields(self, connection: &SqliteConnection) -> Self {
+		match self {
+			Error::UniqueViolation { index: Some(index), fields } if fields.is_empty() => {
+				let fields = connection.query_row(
+					"SELECT sql FROM sqlite_master WHERE type = 'index' AND name = ?",
+					&[&index],
+					|row| row.get::<_, String>(0),
+				).map(|sql| index_fields(&sql)).unwrap_or_default();
+				Error::UniqueViolation { index: Some(index), fields }
+			},
+			error => error,
+		}
+	}
+}
+impl Display for Error {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Error::Sqlite(error) => write!(f, "{}", error),
+			Error::Serialization(error) => write!(f, "could not serialise the value: {}", error),
+			Error::Deserialization { id, path, error } => {
+				write!(f, "could not deserialise the value")?;
+				if let Some(path) = path { write!(f, " at {}", path)?; }
+				if let Some(id) = id { write!(f, " of the entry with the id {:?}", id)?; }
+				write!(f, ": {}", error)
+			},
+			Error::MissingTable(table) => write!(f, "the table {} does not exist", table),
+			Error::InvalidPath(reason) => write!(f, "invalid JSON path: {}", reason),
+			Error::UniqueViolation { index, fields } => {
+				write!(f, "another entry has the same {}", fields.join(", "))?;
+				match index {
+					Some(index) => write!(f, " in the unique index {}", index),
+					None => Ok(()),
+				}
+			},
+			Error::InvalidIdentifier { name, reason } => write!(f, "invalid identifier {:?}: {}", name, reason),
+			Error::InvalidIndexFilter(reason) => write!(f, "the filter can't be used in an index: {}", reason),
+			Error::Disconnected => write!(f, "the connection's thread has stopped"),
+			Error::CannotRebuild { table, reason } => write!(f, "the table {} can't be rebuilt: {}", table, reason),
+		}
+	}
+}
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Sqlite(error) => Some(error),
+			Error::Serialization(error) => Some(error.as_ref()),
+			Error::Deserialization { error, .. } => Some(error.as_ref()),
+			_ => None,
+		}
+	}
+}
+impl From<SqliteError> for Error {
+	fn from(error: SqliteError) -> Self {
+		match error {
+			SqliteError::SqliteFailure(error, message) => failure(error, message),
+			SqliteError::FromSqlConversionFailure(_, _, error) =>
+				Error::Deserialization { id: None, path: None, error },
+			error @ SqliteError::InvalidColumnType(..) | error @ SqliteError::IntegralValueOutOfRange(..) =>
+				Error::Deserialization { id: None, path: None, error: Box::new(error) },
+			SqliteError::ToSqlConversionFailure(error) => match error.downcast::<serde_json::Error>() {
+				Ok(error) => Error::Serialization(Arc::new(*error)),
+				Err(error) => Error::Sqlite(SqliteError::ToSqlConversionFailure(error)),
+			},
+			error => Error::Sqlite(error),
+		}
+	}
+}
+impl From<serde_json::Error> for Error {
+	fn from(error: serde_json::Error) -> Self { Error::Serialization(Arc::new(error)) }
+}
+
+/// Converts an error code from SQLite into the error that it represents.
+fn failure(error: ffi::Error, message: Option<String>) -> Error {
+	let detail = |prefix: &str| message.as_deref().and_then(|message| message.strip_prefix(prefix)).map(String::from);
+	if error.extended_code == ffi::SQLITE_ERROR {
+		// SQLite uses its generic error code for these, so only the message can tell them apart
+		if let Some(table) = detail("no such table: ") { return Error::MissingTable(table); }
+		if detail("JSON path error").is_some() { return Error::InvalidPath(message.unwrap_or_default()); }
+	}
+	if error.extended_code == ffi::SQLITE_CONSTRAINT_UNIQUE || error.extended_code == ffi::SQLITE_CONSTRAINT_PRIMARYKEY {
+		return unique_violation(&detail("UNIQUE constraint failed: ").unwrap_or_default());
+	}
+	Error::Sqlite(SqliteError::SqliteFailure(error, message))
+}
+
+/// Converts a value into an SQL value so that it can be used in errors.
+pub(crate) fn id_value<T: ToSql>(value: &T) -> Option<Value> {
+	match value.to_sql().ok()? {
+		ToSqlOutput::Borrowed(value) => Some(value.into()),
+		ToSqlOutput::Owned(value) => Some(value),
+	}
+}
+
+/// Creates a unique violation from the part of SQLite's message that says what wasn't unique.
+///
+/// This is either `index 'name'` or a list of columns such as `table.a, table.b`.
+fn unique_violation(failed: &str) -> Error {
+	if failed.starts_with("index '") && failed.ends_with('\'') {
+		return Error::UniqueViolation { index: Some(failed["index '".len()..failed.len() - 1].into()), fields: Vec::new() };
+	}
+	let fields = failed.split(", ")
+		.map(|column| column.splitn(2, '.').last().unwrap_or(column).into())
+		.collect();
+	Error::UniqueViolation { index: None, fields }
+}
+
+/// Gets the keys of an index from the SQL that created it.
+///
+/// Keys that are fields in a JSON object are given as their JSON path,
+/// quoted columns are given as their name and any other expression is given as it is.
+///
+/// ```
+/// # use nosqlite::{Connection, Error, json, Table};
+/// # let connection = Connection::in_memory()?;
+/// connection.as_ref().execute_batch(r#"
+/// 	CREATE TABLE people (id INTEGER PRIMARY KEY, "team, role" TEXT DEFAULT 'a', data TEXT NOT NULL);
+/// 	CREATE UNIQUE INDEX people_email ON people ("team, role", lower(json_extract(data, '$.email')));
+/// "#)?;
+/// let table: Table<i64> = Table::unchecked::<i64, _>("id", "data", "people")?;
+/// table.insert(json!({"email": "hiruna@example.com"}), &connection)?;
+/// match table.insert(json!({"email": "Hiruna@Example.com"}), &connection) {
+/// 	Err(Error::UniqueViolation { fields, .. }) =>
+/// 		assert_eq!(fields, ["team, role", "lower(json_extract(data, '$.email'))"]),
+/// 	_ => panic!("Expected the email to already be used"),
+/// }
+/// # nosqlite::Result::Ok(())
+/// ```
+pub(crate) fn index_fields(sql: &str) -> Vec<String> {
+	// The keys are inside the first brackets that aren't in quotes
+	let (mut depth, mut quote, mut start) = (0, None, None);
+	let mut keys = Vec::new();
+	for (i, c) in sql.char_indices() {
+		match (quote, c) {
+			(Some(q), c) if c == q => quote = None,
+			(Some(_), _) => {},
+			(None, '\'') | (None, '"') => quote = Some(c),
+			(None, '(') => {
+				depth += 1;
+				if depth == 1 { start = Some(i + 1); }
+			},
+			(None, ',') if depth == 1 => {
+				keys.push(&sql[start.unwrap_or(i)..i]);
+				start = Some(i + 1);
+			},
+			(None, ')') => {
+				depth -= 1;
+				if depth == 0 {
+					keys.push(&sql[start.unwrap_or(i)..i]);
+					break;
+				}
+			},
+			_ => {},
+		}
+	}
+	keys.into_iter().map(|key| {
+		let key = key.trim();
+		// A JSON field is rendered as `json_extract(data, 'path')`
+		match (key.starts_with("json_extract("), key.rfind(", '")) {
+			(true, Some(path)) => key[path + 3..key.len() - 2].replace("''", "'"),
+			_ => unquote(key).unwrap_or_else(|| key.into()),
+		}
+	}).collect()
+}
+
+/// Gets the name of an identifier in double quotes, if the whole key is one.
+fn unquote(key: &str) -> Option<String> {
+	let name = key.strip_prefix('"')?.strip_suffix('"')?;
+	Some(name.replace("\"\"", "\"")).filter(|_| !name.replace("\"\"", "").contains('"'))
+}
@@ -0,0 +1,160 @@
+use serde::Serialize;
+
+use std::marker::PhantomData;
+
+use crate::{Field, Key, util::{Between, Contains, Eq, Gt, Gte, In, Neq, NotIn, Param}, ArrayLength};
+
+/// A type whose fields can be referred to without using strings.
+///
+/// This is usually implemented using `#[derive(Fields)]`, which needs the `derive` feature.
+/// The derive also creates an associated function for each field of the struct
+/// that refers to the field in the JSON object, e.g. `Person::age()`.
+/// A field that is also a struct that implements this trait can be marked with `#[fields(nested)]`
+/// to refer to its fields, e.g. `Person::address().city()`.
+///
+/// The names of the fields follow serde's `rename`, `rename_all`, `skip` and `flatten` attributes.
+pub trait Fields {
+	/// The type that refers to the fields of this type.
+	type Fields;
+
+	/// Refers to the fields of a value that is at the given JSON path.
+	fn fields(path: String) -> Self::Fields;
+}
+
+/// A field in a JSON object that has a value of type `T`.
+///
+/// This can be used anywhere a [`Key`] can be used
+/// but its comparisons only accept values that can be compared with `T`,
+/// so a comparison with a value of the wrong type is a compile error.
+///
+/// [`Key`]: trait.Key.html
+///
+/// # Example
+///
+/// ```
+/// # use nosqlite::{Connection, json, TypedField};
+/// # let connection = Connection::in_memory()?;
+/// # let table = connection.table("people")?;
+/// table.insert(json!({"name": "Hiruna", "age": 19}), &connection)?;
+/// table.insert(json!({"name": "Bob", "age": 13}), &connection)?;
+/// let age: TypedField<u8> = TypedField::new("age");
+/// let name: TypedField<String> = TypedField::new("name");
+/// let names: Vec<String> = table.iter().filter(age.gt(18)).field(name.path(), &connection)?;
+/// assert_eq!(names, ["Hiruna"]);
+/// # nosqlite::Result::Ok(())
+/// ```
+///
+/// ```compile_fail
+/// # use nosqlite::TypedField;
+/// let age: TypedField<u8> = TypedField::new("age");
+/// age.gt("eighteen");
+/// ```
+pub struct TypedField<T> {
+	field: Field,
+	value_type: PhantomData<fn() -> T>,
+}
+impl<T> TypedField<T> {
+	/// Refers to a field using its JSON path.
+	///
+	/// The path is formatted in the same way as [`field`].
+	///
+	/// [`field`]: fn.field.html
+	pub fn new<S: AsRef<str>>(path: S) -> Self {
+		Self { field: crate::field(path.as_ref()), value_type: PhantomData }
+	}
+
+	/// The JSON path of the field.
+	pub fn path(&self) -> &str { &self.field.0 }
+
+	/// Compares for equality.
+	///
+	/// See [`Key::eq`] for more details.
+	///
+	/// [`Key::eq`]: trait.Key.html#method.eq
+	pub fn eq<V: Comparable<T>>(self, value: V) -> Eq<Self, Param> { Key::eq(self, value) }
+
+	/// Compares for inequality.
+	///
+	/// See [`Key::neq`] for more details.
+	///
+	/// [`Key::neq`]: trait.Key.html#method.neq
+	pub fn neq<V: Comparable<T>>(self, value: V) -> Neq<Self, Param> { Key::neq(self, value) }
+
+	/// Compares if it is greater than the value.
+	pub fn gt<V: Comparable<T>>(self, value: V) -> Gt<Self, Param> { Key::gt(self, value) }
+
+	/// Compares if it is greater than or equal to the value.
+	pub fn gte<V: Comparable<T>>(self, value: V) -> Gte<Self, Param> { Key::gte(self, value) }
+
+	/// Compares if it is less than the value.
+	pub fn lt<V: Comparable<T>>(self, value: V) -> Gt<Param, Self> { Key::lt(self, value) }
+
+	/// Compares if it is less than or equal to the value.
+	pub fn lte<V: Comparable<T>>(self, value: V) -> Gte<Param, Self> { Key::lte(self, value) }
+
+	/// Compares whether it is equal to any of the values.
+	///
+	/// See [`Key::in_`] for more details.
+	///
+	/// [`Key::in_`]: trait.Key.html#method.in_
+	pub fn in_<I, V>(self, values: I) -> In<Self>
+	where
+		I: IntoIterator<Item=V>,
+		V: Comparable<T>,
+	{
+		Key::in_(self, values)
+	}
+
+	/// Compares whether it is not equal to any of the values.
+	pub fn not_in<I, V>(self, values: I) -> NotIn<Self>
+	where
+		I: IntoIterator<Item=V>,
+		V: Comparable<T>,
+	{
+		Key::not_in(self, values)
+	}
+
+	/// Compares whether it is between the two values, including the values themselves.
+	pub fn between<V: Comparable<T>>(self, low: V, high: V) -> Between<Self> { Key::between(self, low, high) }
+}
+impl<T> TypedField<Vec<T>> {
+	/// Whether the array contains the value.
+	///
+	/// See [`Field::contains`] for more details.
+	///
+	/// [`Field::contains`]: struct.Field.html#method.contains
+	pub fn contains<V: Comparable<T>>(self, value: V) -> Contains { self.field.contains(value) }
+
+	/// Whether the array contains at least one of the values.
+	pub fn contains_any<I, V>(self, values: I) -> Contains
+	where
+		I: IntoIterator<Item=V>,
+		V: Comparable<T>,
+	{
+		self.field.contains_any(values)
+	}
+
+	/// Whether the array contains all of the values.
+	pub fn contains_all<I, V>(self, values: I) -> Contains
+	where
+		I: IntoIterator<Item=V>,
+		V: Comparable<T>,
+	{
+		self.field.contains_all(values)
+	}
+
+	/// The length of the array.
+	pub fn array_len(self) -> ArrayLength { self.field.array_len() }
+}
+impl<T> Key for TypedField<T> {
+	fn key(&self, data_key: &str) -> String { self.field.key(data_key) }
+	fn json_type(&self, data_key: &str) -> Option<String> { self.field.json_type(data_key) }
+}
+
+/// A value that can be compared with a value of type `T`.
+///
+/// This is implemented for `T` itself, references to `T` and string slices for `String`.
+pub trait Comparable<T>: Serialize {}
+impl<T: Serialize> Comparable<T> for T {}
+impl<T: Serialize> Comparable<T> for &T {}
+impl Comparable<String> for &str {}
@@ -0,0 +1,156 @@
+use rusqlite::{Connection as SqliteConnection, types::FromSql};
+
+use crate::{Clause, Filter, Iterator, Key, Result, Sort};
+
+/// Entries that have been grouped by a key so that they can be aggregated per group.
+///
+/// Create this using [`Iterator::group_by`].
+/// Each aggregate returns the value of the key for each group along with the aggregated value,
+/// sorted by the value of the key.
+///
+/// [`Iterator::group_by`]: struct.Iterator.html#method.group_by
+#[must_use = "This struct does not do anything until an aggregate is used"]
+pub struct GroupBy<'i, 'a, I, W, S> {
+	pub(crate) iterator: &'i Iterator<'a, I, W, S>,
+	pub(crate) key: String,
+}
+impl<I: FromSql, W: Filter, S: Sort> GroupBy<'_, '_, I, W, S> {
+	/// Counts the entries in each group.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna", "age": 19}), &connection)?;
+	/// table.insert(json!({"name": "Bob", "age": 13}), &connection)?;
+	/// table.insert(json!({"name": "Callum", "age": 19}), &connection)?;
+	/// let ages: Vec<(u8, usize)> = table.iter()
+	/// 	.filter(field("name").neq("Callum"))
+	/// 	.group_by(field("age"))
+	/// 	.count(&connection)?;
+	/// assert_eq!(ages, [(13, 1), (19, 1)]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn count<G: FromSql, C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<Vec<(G, usize)>> {
+		let groups: Vec<(G, i64)> = self.aggregate("count(*)", connection)?;
+		Ok(groups.into_iter().map(|(group, count)| (group, count as usize)).collect())
+	}
+
+	/// Adds up a key of the entries in each group.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("orders")?;
+	/// table.insert(json!({"customer": "Hiruna", "total": 10}), &connection)?;
+	/// table.insert(json!({"customer": "Bob", "total": 5}), &connection)?;
+	/// table.insert(json!({"customer": "Hiruna", "total": 7}), &connection)?;
+	/// let totals: Vec<(String, i64)> = table.iter().group_by(field("customer")).sum(field("total"), &connection)?;
+	/// assert_eq!(totals, [("Bob".into(), 5), ("Hiruna".into(), 17)]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn sum<K, G, T, C>(&self, key: K, connection: C) -> Result<Vec<(G, T)>>
+	where
+		K: Key,
+		G: FromSql,
+		T: FromSql,
+		C: AsRef<SqliteConnection>,
+	{
+		self.aggregate(&format!("sum({})", key.key(self.iterator.data_key)), connection)
+	}
+
+	/// Finds the average of a key of the entries in each group.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("orders")?;
+	/// table.insert(json!({"customer": "Hiruna", "total": 10}), &connection)?;
+	/// table.insert(json!({"customer": "Bob", "total": 5}), &connection)?;
+	/// table.insert(json!({"customer": "Hiruna", "total": 7}), &connection)?;
+	/// let averages: Vec<(String, f64)> = table.iter().group_by(field("customer")).avg(field("total"), &connection)?;
+	/// assert_eq!(averages, [("Bob".into(), 5.0), ("Hiruna".into(), 8.5)]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn avg<K, G, C>(&self, key: K, connection: C) -> Result<Vec<(G, f64)>>
+	where
+		K: Key,
+		G: FromSql,
+		C: AsRef<SqliteConnection>,
+	{
+		self.aggregate(&format!("avg({})", key.key(self.iterator.data_key)), connection)
+	}
+
+	/// Finds the smallest value of a key of the entries in each group.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna", "city": "Auckland", "age": 19}), &connection)?;
+	/// table.insert(json!({"name": "Bob", "city": "Wellington", "age": 13}), &connection)?;
+	/// table.insert(json!({"name": "Callum", "city": "Auckland", "age": 25}), &connection)?;
+	/// let youngest: Vec<(String, u8)> = table.iter().group_by(field("city")).min(field("age"), &connection)?;
+	/// assert_eq!(youngest, [("Auckland".into(), 19), ("Wellington".into(), 13)]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn min<K, G, T, C>(&self, key: K, connection: C) -> Result<Vec<(G, T)>>
+	where
+		K: Key,
+		G: FromSql,
+		T: FromSql,
+		C: AsRef<SqliteConnection>,
+	{
+		self.aggregate(&format!("min({})", key.key(self.iterator.data_key)), connection)
+	}
+
+	/// Finds the largest value of a key of the entries in each group.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna", "city": "Auckland", "age": 19}), &connection)?;
+	/// table.insert(json!({"name": "Bob", "city": "Wellington", "age": 13}), &connection)?;
+	/// table.insert(json!({"name": "Callum", "city": "Auckland", "age": 25}), &connection)?;
+	/// let oldest: Vec<(String, u8)> = table.iter().group_by(field("city")).max(field("age"), &connection)?;
+	/// assert_eq!(oldest, [("Auckland".into(), 25), ("Wellington".into(), 13)]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn max<K, G, T, C>(&self, key: K, connection: C) -> Result<Vec<(G, T)>>
+	where
+		K: Key,
+		G: FromSql,
+		T: FromSql,
+		C: AsRef<SqliteConnection>,
+	{
+		self.aggregate(&format!("max({})", key.key(self.iterator.data_key)), connection)
+	}
+
+	/// Aggregates the entries in each group using the given SQL expression.
+	fn aggregate<G, T, C>(&self, expression: &str, connection: C) -> Result<Vec<(G, T)>>
+	where
+		G: FromSql,
+		T: FromSql,
+		C: AsRef<SqliteConnection>,
+	{
+		let Clause { sql, params } = self.iterator.aggregate(
+			&format!("{}, {}", self.key, expression),
+			&format!("GROUP BY {0} ORDER BY {0}", self.key),
+		)?;
+		let mut statement = connection.as_ref().prepare(&sql)?;
+		let groups = statement.query_map(&params, |row| Ok((row.get(0)?, row.get(1)?)))?
+			.collect::<rusqlite::Result<_>>()?;
+		Ok(groups)
+	}
+}
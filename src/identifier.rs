@@ -0,0 +1,73 @@
+use crate::{Error, Result};
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// The name of a table, column or index in the database.
+///
+/// The name is always quoted when it is put into an SQL statement,
+/// so names with dashes, spaces, or names that are reserved words (e.g. `order`) can be used.
+///
+/// # Example
+///
+/// ```
+/// # use nosqlite::{Connection, field, Identifier, json};
+/// assert_eq!(Identifier::new("order")?.to_string(), r#""order""#);
+/// assert_eq!(Identifier::new(r#"my "table""#)?.to_string(), r#""my ""table""""#);
+/// // Names that SQLite can't use are rejected
+/// assert!(Identifier::new("").is_err());
+/// assert!(Identifier::new("sqlite_master").is_err());
+///
+/// # let connection = Connection::in_memory()?;
+/// let table = connection.table("tenant-orders")?;
+/// table.insert(json!({"order": 1}), &connection)?;
+/// table.index("order", &[field("order")], &connection)?;
+/// assert_eq!(table.iter().count(&connection)?, 1);
+/// # nosqlite::Result::Ok(())
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Identifier {
+	name: String,
+	quoted: String,
+}
+impl Identifier {
+	/// Creates an identifier, checking that it is a valid name.
+	///
+	/// A name is invalid if it is empty, contains a nul character,
+	/// or starts with `sqlite_` which is reserved for SQLite's internal use.
+	pub fn new<S: Into<String>>(name: S) -> Result<Self> {
+		let name = name.into();
+		let reason = if name.is_empty() {
+			Some("identifiers cannot be empty")
+		} else if name.contains('\0') {
+			Some("identifiers cannot contain a nul character")
+		} else if name.len() >= 7 && name.as_bytes()[..7].eq_ignore_ascii_case(b"sqlite_") {
+			Some("identifiers starting with `sqlite_` are reserved for SQLite")
+		} else { None };
+		match reason {
+			Some(reason) => Err(Error::InvalidIdentifier { name, reason }),
+			None => Ok(Self { quoted: quote(&name), name }),
+		}
+	}
+
+	/// The name without any quotes.
+	pub fn as_str(&self) -> &str { &self.name }
+
+	/// The name quoted so that it can be used in an SQL statement.
+	pub fn quoted(&self) -> &str { &self.quoted }
+}
+impl Display for Identifier {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult { f.write_str(&self.quoted) }
+}
+impl AsRef<str> for Identifier {
+	fn as_ref(&self) -> &str { &self.name }
+}
+
+/// Quotes a name so that it can be used as an identifier in an SQL statement.
+pub(crate) fn quote(name: &str) -> String {
+	format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quotes a string so that it can be used as a string literal in an SQL statement.
+pub(crate) fn quote_literal(literal: &str) -> String {
+	format!("'{}'", literal.replace('\'', "''"))
+}
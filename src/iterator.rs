@@ -1,10 +1,10 @@
 use std::marker::PhantomData;
 
-use rusqlite::{Connection as SqliteConnection, Result as SqliteResult, Statement,
-	types::{FromSql, ToSql}};
+use rusqlite::{Connection as SqliteConnection, Row, Statement, types::{FromSql, ToSql, Value}};
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::{Entry, field, Filter, format_key, Json, Key, Sort};
+use crate::{And, Clause, Entry, Error, field, Filter, format_key, Generated, GroupBy, Json, Key, Query, quote, quote_literal,
+	Result, Search, search_index, Sort, Stream, with_generated};
 
 /// Represents a potential operation on a table.
 #[must_use = "This struct does not do anything until executed"]
@@ -17,10 +17,19 @@ pub struct Iterator<'a, I, W, S> {
 	pub(crate) order_by: S,
 	pub(crate) where_: W,
 	pub(crate) table_key: &'a str,
+	pub(crate) table_name: &'a str,
+	pub(crate) generated: Generated,
 }
 impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// ***GET***s only the JSON object.
 	///
+	/// If any JSON object can't be deserialised, the first error is returned
+	/// along with the id of the entry which caused it (see [`Error::Deserialization`]).
+	/// Use [`try_data`] if those entries should be skipped instead.
+	///
+	/// [`Error::Deserialization`]: enum.Error.html#variant.Deserialization
+	/// [`try_data`]: #method.try_data
+	///
 	/// # Example
 	///
 	/// ```
@@ -33,18 +42,95 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// # 	name: String,
 	/// # }
 	/// let people: Vec<Person> = table.iter().data(&connection)?;
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn data<T: DeserializeOwned, C: AsRef<SqliteConnection>>(&self, connection: C) -> SqliteResult<Vec<T>> {
+	pub fn data<T: DeserializeOwned, C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<Vec<T>> {
+		self.try_data(connection)?.into_iter().collect()
+	}
+
+	/// ***GET***s only the JSON object, with a separate result for each entry.
+	///
+	/// Unlike [`data`], an entry that can't be deserialised doesn't stop the other entries from being returned.
+	///
+	/// [`data`]: #method.data
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json, Table};
+	/// # use serde::{Deserialize, Serialize};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// #[derive(Deserialize, Serialize)]
+	/// struct Person {
+	/// 	name: String,
+	/// }
+	/// table.insert(json!({"name": "Hiruna"}), &connection)?;
+	/// table.insert(json!({"age": 13}), &connection)?;
+	/// // The second entry isn't a `Person` so `data` fails
+	/// assert!(table.iter().data::<Person, _>(&connection).is_err());
+	/// // But the entries that are a `Person` can still be used
+	/// let people: Vec<Person> = table.iter().try_data(&connection)?
+	/// 	.into_iter()
+	/// 	.filter_map(Result::ok)
+	/// 	.collect();
+	/// assert_eq!(people.len(), 1);
+	/// assert_eq!(people[0].name, "Hiruna");
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn try_data<T, C>(&self, connection: C) -> Result<Vec<Result<T>>>
+	where
+		T: DeserializeOwned,
+		C: AsRef<SqliteConnection>,
+	{
 		self.execute::<_, _, _>(
-			&format!("SELECT {}", self.data_key),
-			get_first_column(Json::unwrap),
+			&format!("SELECT {}, {}", self.id_key, self.data_key),
+			get_second_column("$", Json::unwrap),
 			connection
 		)
 	}
 
+	/// Prepares a query which reads the entries one at a time instead of collecting them all.
+	///
+	/// Use this instead of [`data`] or [`entry`] when there are too many entries to keep in memory at once.
+	/// Filters, sorting, skipping and taking work the same way.
+	///
+	/// [`data`]: #method.data
+	/// [`entry`]: #method.entry
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json, Table};
+	/// # use serde::{Deserialize, Serialize};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// #[derive(Deserialize, Serialize)]
+	/// struct Person {
+	/// 	name: String,
+	/// }
+	/// table.insert(json!({"name": "Hiruna"}), &connection)?;
+	/// table.insert(json!({"name": "Bob"}), &connection)?;
+	/// let mut stream = table.iter().skip(1).stream(&connection)?;
+	/// for person in stream.data::<Person>()? {
+	/// 	assert_eq!(person?.name, "Bob");
+	/// }
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn stream<'c, C: AsRef<SqliteConnection>>(&self, connection: &'c C) -> Result<Stream<'c, I>> {
+		let clauses = self.query()?.clauses();
+		let statement = connection.as_ref().prepare(&format!("SELECT {}, {} FROM {} {}",
+			self.id_key, self.data_key, self.table_key, clauses.sql))?;
+		Ok(Stream { statement, params: clauses.params, id_type: self.id_type })
+	}
+
 	/// ***GET***s the id and the JSON object.
 	///
+	/// If any JSON object can't be deserialised, the first error is returned.
+	/// Use [`try_entry`] if those entries should be skipped instead.
+	///
+	/// [`try_entry`]: #method.try_entry
+	///
 	/// # Example
 	///
 	/// ```
@@ -57,16 +143,29 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// # 	name: String,
 	/// # }
 	/// let people: Vec<Entry<i64, Person>> = table.iter().entry(&connection)?;
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn entry<T: DeserializeOwned, C: AsRef<SqliteConnection>>(&self, connection: C) -> SqliteResult<Vec<Entry<I, T>>> {
+	pub fn entry<T: DeserializeOwned, C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<Vec<Entry<I, T>>> {
+		self.try_entry(connection)?.into_iter().collect()
+	}
+
+	/// ***GET***s the id and the JSON object, with a separate result for each entry.
+	///
+	/// Unlike [`entry`], an entry that can't be deserialised doesn't stop the other entries from being returned.
+	///
+	/// [`entry`]: #method.entry
+	pub fn try_entry<T, C>(&self, connection: C) -> Result<Vec<Result<Entry<I, T>>>>
+	where
+		T: DeserializeOwned,
+		C: AsRef<SqliteConnection>,
+	{
 		self.execute::<_, _, _>(
 			&format!("SELECT {}, {}", self.id_key, self.data_key),
 			|mut statement, params| {
-				Ok(statement.query_map_named(
+				Ok(statement.query_and_then(
 					&params,
 					Entry::from_row,
-				)?.filter_map(Result::ok).collect::<Vec<_>>())
+				)?.collect::<Vec<_>>())
 			},
 			connection
 		)
@@ -86,18 +185,155 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// # 	name: String,
 	/// # }
 	/// let people: Vec<i64> = table.iter().id(&connection)?;
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn id<C: AsRef<SqliteConnection>>(&self, connection: C) -> SqliteResult<Vec<I>> {
+	pub fn id<C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<Vec<I>> {
 		self.execute::<_, _, _>(
 			&format!("SELECT {}", self.id_key),
-			get_first_column(no_map),
+			|mut statement, params| Ok(statement.query_map(&params, |row| row.get(0))?.collect::<rusqlite::Result<_>>()?),
 			connection
 		)
 	}
 
+	/// Counts the entries.
+	///
+	/// Only the number is read from the database, not the entries themselves.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna", "age": 19}), &connection)?;
+	/// table.insert(json!({"name": "Bob", "age": 13}), &connection)?;
+	/// table.insert(json!({"name": "Callum", "age": 25}), &connection)?;
+	/// assert_eq!(table.iter().count(&connection)?, 3);
+	/// assert_eq!(table.iter().filter(field("age").gte(18)).count(&connection)?, 2);
+	/// assert_eq!(table.iter().skip(1).take(5).count(&connection)?, 2);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn count<C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<usize> {
+		let count: i64 = self.aggregate_one("count(*)", connection)?;
+		Ok(count as usize)
+	}
+
+	/// Adds up a key of every entry.
+	///
+	/// Returns `None` if there are no entries to add up.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna", "age": 19}), &connection)?;
+	/// table.insert(json!({"name": "Bob", "age": 13}), &connection)?;
+	/// table.insert(json!({"name": "Callum", "age": 25}), &connection)?;
+	/// let total: Option<i64> = table.iter().sum(field("age"), &connection)?;
+	/// assert_eq!(total, Some(57));
+	/// let total: Option<i64> = table.iter().filter(field("age").gt(100)).sum(field("age"), &connection)?;
+	/// assert_eq!(total, None);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn sum<K: Key, T: FromSql, C: AsRef<SqliteConnection>>(&self, key: K, connection: C) -> Result<Option<T>> {
+		self.aggregate_one(&format!("sum({})", key.key(self.data_key)), connection)
+	}
+
+	/// Finds the average of a key of every entry.
+	///
+	/// Returns `None` if there are no entries.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna", "age": 19}), &connection)?;
+	/// table.insert(json!({"name": "Bob", "age": 13}), &connection)?;
+	/// assert_eq!(table.iter().avg(field("age"), &connection)?, Some(16.0));
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn avg<K: Key, C: AsRef<SqliteConnection>>(&self, key: K, connection: C) -> Result<Option<f64>> {
+		self.aggregate_one(&format!("avg({})", key.key(self.data_key)), connection)
+	}
+
+	/// Finds the smallest value of a key of every entry.
+	///
+	/// Returns `None` if there are no entries.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna", "age": 19}), &connection)?;
+	/// table.insert(json!({"name": "Bob", "age": 13}), &connection)?;
+	/// let youngest: Option<u8> = table.iter().min(field("age"), &connection)?;
+	/// assert_eq!(youngest, Some(13));
+	/// let first: Option<String> = table.iter().min(field("name"), &connection)?;
+	/// assert_eq!(first.as_deref(), Some("Bob"));
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn min<K: Key, T: FromSql, C: AsRef<SqliteConnection>>(&self, key: K, connection: C) -> Result<Option<T>> {
+		self.aggregate_one(&format!("min({})", key.key(self.data_key)), connection)
+	}
+
+	/// Finds the largest value of a key of every entry.
+	///
+	/// Returns `None` if there are no entries.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna", "age": 19}), &connection)?;
+	/// table.insert(json!({"name": "Bob", "age": 13}), &connection)?;
+	/// let oldest: Option<u8> = table.iter().max(field("age"), &connection)?;
+	/// assert_eq!(oldest, Some(19));
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn max<K: Key, T: FromSql, C: AsRef<SqliteConnection>>(&self, key: K, connection: C) -> Result<Option<T>> {
+		self.aggregate_one(&format!("max({})", key.key(self.data_key)), connection)
+	}
+
+	/// Groups the entries by a key so that they can be aggregated per group.
+	///
+	/// See [`GroupBy`] for the aggregates that can be used.
+	///
+	/// [`GroupBy`]: struct.GroupBy.html
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna", "city": "Auckland"}), &connection)?;
+	/// table.insert(json!({"name": "Bob", "city": "Wellington"}), &connection)?;
+	/// table.insert(json!({"name": "Callum", "city": "Auckland"}), &connection)?;
+	/// let cities: Vec<(String, usize)> = table.iter().group_by(field("city")).count(&connection)?;
+	/// assert_eq!(cities, [("Auckland".into(), 2), ("Wellington".into(), 1)]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn group_by<K: Key>(&self, key: K) -> GroupBy<'_, 'a, I, W, S> {
+		GroupBy { iterator: self, key: key.key(self.data_key) }
+	}
+
 	/// ***GET***s a field of the JSON object.
 	///
+	/// If any field can't be converted, the first error is returned
+	/// along with the id of the entry which caused it (see [`Error::Deserialization`]).
+	/// Use [`try_field`] if those entries should be skipped instead.
+	///
+	/// [`Error::Deserialization`]: enum.Error.html#variant.Deserialization
+	/// [`try_field`]: #method.try_field
+	///
 	/// # Example
 	///
 	/// ```
@@ -114,18 +350,54 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// table.insert(Person{ first_name: "Hiruna".into(), last_name: "Jayamanne".into(), age: 19 }, &connection)?;
 	/// let people: Vec<String> = table.iter().field("first_name", &connection)?;
 	/// assert_eq!(people[0], "Hiruna");
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn field<T: FromSql, C: AsRef<SqliteConnection>>(&self, field_: &str, connection: C) -> Result<Vec<T>> {
+		self.try_field(field_, connection)?.into_iter().collect()
+	}
+
+	/// ***GET***s a field of the JSON object, with a separate result for each entry.
+	///
+	/// Unlike [`field`], an entry whose field can't be converted
+	/// doesn't stop the other entries from being returned.
+	///
+	/// [`field`]: #method.field
+	///
+	/// # Example
+	///
 	/// ```
-	pub fn field<T: FromSql, C: AsRef<SqliteConnection>>(&self, field_: &str, connection: C) -> SqliteResult<Vec<T>> {
+	/// # use nosqlite::{Connection, json, Table};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna", "age": 19}), &connection)?;
+	/// table.insert(json!({"name": "Bob"}), &connection)?;
+	/// // Bob doesn't have an age so it can't be converted into a number
+	/// let ages: Vec<_> = table.iter().try_field::<u8, _>("age", &connection)?;
+	/// assert_eq!(ages[0].as_ref().ok(), Some(&19));
+	/// assert!(ages[1].is_err());
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn try_field<T, C>(&self, field_: &str, connection: C) -> Result<Vec<Result<T>>>
+	where
+		T: FromSql,
+		C: AsRef<SqliteConnection>,
+	{
 		self.execute::<_, _, _>(
-			&format!("SELECT {}", field(field_).key(&self.data_key)),
-			get_first_column(no_map),
+			&format!("SELECT {}, {}", self.id_key, field(field_).key(self.data_key)),
+			get_second_column(&format_key(field_), no_map),
 			connection
 		)
 	}
 
 	/// ***GET***s multiple fields from the JSON object.
 	///
+	/// If the fields of any entry can't be deserialised, the first error is returned
+	/// along with the id of the entry which caused it (see [`Error::Deserialization`]).
+	/// Use [`try_fields`] if those entries should be skipped instead.
+	///
+	/// [`Error::Deserialization`]: enum.Error.html#variant.Deserialization
+	/// [`try_fields`]: #method.try_fields
+	///
 	/// # Example
 	///
 	/// ```
@@ -142,26 +414,41 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// table.insert(Person{ first_name: "Hiruna".into(), last_name: "Jayamanne".into(), age: 19 }, &connection)?;
 	/// let people: Vec<(String, String)> = table.iter().fields(&["first_name", "last_name"], &connection)?;
 	/// assert_eq!(people[0], ("Hiruna".into(), "Jayamanne".into()));
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn fields<T, F, C, A>(&self, fields: F, connection: C) -> SqliteResult<Vec<T>>
+	pub fn fields<T, F, C, A>(&self, fields: F, connection: C) -> Result<Vec<T>>
 	where
 		F: IntoIterator<Item=A>,
 		A: AsRef<str>,
 		T: DeserializeOwned,
 		C: AsRef<SqliteConnection>,
 	{
-		let fields = fields.into_iter()
-			.map(|s| format_key(s.as_ref()))
-			.fold(String::new(), |mut init, field| {
-				init.push_str(",\"");
-				init.push_str(field.as_str());
-				init.push('"');
+		self.try_fields(fields, connection)?.into_iter().collect()
+	}
+
+	/// ***GET***s multiple fields from the JSON object, with a separate result for each entry.
+	///
+	/// Unlike [`fields`], an entry whose fields can't be deserialised
+	/// doesn't stop the other entries from being returned.
+	///
+	/// [`fields`]: #method.fields
+	pub fn try_fields<T, F, C, A>(&self, fields: F, connection: C) -> Result<Vec<Result<T>>>
+	where
+		F: IntoIterator<Item=A>,
+		A: AsRef<str>,
+		T: DeserializeOwned,
+		C: AsRef<SqliteConnection>,
+	{
+		let paths = fields.into_iter().map(|s| format_key(s.as_ref())).collect::<Vec<_>>();
+		let fields = paths.iter()
+			.fold(String::new(), |mut init, path| {
+				init.push(',');
+				init.push_str(&quote_literal(path));
 				init
 			});
 		self.execute::<_, _, _>(
-			&format!("SELECT json_extract({}{})", self.data_key, fields),
-			get_first_column(Json::unwrap),
+			&format!("SELECT {}, json_extract({}{})", self.id_key, self.data_key, fields),
+			get_second_column(&paths.join(", "), Json::unwrap),
 			connection
 		)
 	}
@@ -189,19 +476,16 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// let people: Vec<(String, u8)> = table.iter().fields(&["first_name", "age"], &connection)?;
 	/// assert_eq!(people[0], ("Hiruna".into(), 19));
 	/// assert_eq!(people[1], ("Bob".into(), 13)); // Only Bob was changed
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn insert<T, C>(&self, field: &str, value: T, connection: C) -> SqliteResult<()>
+	pub fn insert<T, C>(&self, field: &str, value: T, connection: C) -> Result<()>
 	where
 		T: ToSql,
 		C: AsRef<SqliteConnection>,
 	{
 		let path = format_key(field);
-		let set_value = format!("{} = json_insert({},\"{}\",:value)", self.data_key, self.data_key, path);
-		connection.as_ref().execute_named(
-			&format!("UPDATE {} SET {} {}", self.table_key, set_value, self.make_clauses()),
-			&[(":value", &value)]
-		).map(|_|())
+		let set_value = format!("{} = json_insert({}, ?, ?)", self.data_key, self.data_key);
+		self.update(&set_value, &[&path, &value], connection)
 	}
 
 	/// Uses a JSON object update or create fields in the entry's JSON object.
@@ -232,18 +516,15 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// 	// `grades` field was overwritten and set to an array of one element
 	/// 	assert_eq!(person.1, [9])
 	/// }
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn patch<T, C>(&self, value: T, connection: C) -> SqliteResult<()>
+	pub fn patch<T, C>(&self, value: T, connection: C) -> Result<()>
 	where
 		T: Serialize,
 		C: AsRef<SqliteConnection>,
 	{
-		let set_value = format!("{} = json_patch({},:value)", self.data_key, self.data_key);
-		connection.as_ref().execute_named(
-			&format!("UPDATE {} SET {} {}", self.table_key, set_value, self.make_clauses()),
-			&[(":value", &Json(value))]
-		).map(|_|())
+		let set_value = format!("{} = json_patch({}, ?)", self.data_key, self.data_key);
+		self.update(&set_value, &[&Json(value)], connection)
 	}
 
 	/// Removes a *field* from a JSON object.
@@ -259,22 +540,19 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// table.insert(json!({ "first_name": "Bob", "age": 13 }), &connection)?;
 	///
 	/// table.iter().remove("age", &connection);
-	/// // This should return no entries because no JSON object has an `age` field
-	/// let people: Vec<u8> = table.iter().field("age", &connection)?;
-	/// assert_eq!(people.len(), 0);
+	/// // No JSON object has an `age` field anymore
+	/// let people: Vec<Option<u8>> = table.iter().field("age", &connection)?;
+	/// assert_eq!(people, [None, None]);
 	/// // This *does not* delete the entries
-	/// assert_eq!(table.iter().id(&connection)?.len(), 2);
-	/// # rusqlite::Result::Ok(())
+	/// assert_eq!(table.iter().count(&connection)?, 2);
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn remove<C>(&self, field: &str, connection: C) -> SqliteResult<()>
+	pub fn remove<C>(&self, field: &str, connection: C) -> Result<()>
 	where C: AsRef<SqliteConnection>
 	{
 		let path = format_key(field);
-		let set_value = format!("{} = json_remove({}, '{}')", self.data_key, self.data_key, path);
-		connection.as_ref().execute(
-			&format!("UPDATE {} SET {} {}", self.table_key, set_value, self.make_clauses()),
-			rusqlite::NO_PARAMS
-		).map(|_|())
+		let set_value = format!("{} = json_remove({}, ?)", self.data_key, self.data_key);
+		self.update(&set_value, &[&path], connection)
 	}
 
 	/// Replaces a field in a JSON object with a given value.
@@ -294,23 +572,19 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// table.insert(json!({ "first_name": "Bob" }), &connection)?;
 	/// // `replace` should only work for objects which have the field already
 	/// table.iter().replace("age", 13, &connection);
-	/// let people: Vec<u8> = table.iter().field("age", &connection)?;
-	/// // Both objects had their fields set
-	/// assert_eq!(people.len(), 1);
-	/// assert_eq!(people[0], 13);
-	/// # rusqlite::Result::Ok(())
+	/// let people: Vec<Option<u8>> = table.iter().field("age", &connection)?;
+	/// // Only the first object had its field replaced
+	/// assert_eq!(people, [Some(13), None]);
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn replace<T, C>(&self, field: &str, value: T, connection: C) -> SqliteResult<()>
+	pub fn replace<T, C>(&self, field: &str, value: T, connection: C) -> Result<()>
 	where
 		T: ToSql,
 		C: AsRef<SqliteConnection>,
 	{
 		let path = format_key(field);
-		let set_value = format!("{} = json_replace({},\"{}\",:value)", self.data_key, self.data_key, path);
-		connection.as_ref().execute_named(
-			&format!("UPDATE {} SET {} {}", self.table_key, set_value, self.make_clauses()),
-			&[(":value", &value)]
-		).map(|_|())
+		let set_value = format!("{} = json_replace({}, ?, ?)", self.data_key, self.data_key);
+		self.update(&set_value, &[&path, &value], connection)
 	}
 
 	/// Sets a field in a JSON object to a given field.
@@ -337,19 +611,16 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// // Both objects had their fields set
 	/// assert_eq!(people[0], 13);
 	/// assert_eq!(people[1], 13);
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn set<T, C>(&self, field: &str, value: T, connection: C) -> SqliteResult<()>
+	pub fn set<T, C>(&self, field: &str, value: T, connection: C) -> Result<()>
 	where
 		T: ToSql,
 		C: AsRef<SqliteConnection>,
 	{
 		let path = format_key(field);
-		let set_value = format!("{} = json_set({},\"{}\",:value)", self.data_key, self.data_key, path);
-		connection.as_ref().execute_named(
-			&format!("UPDATE {} SET {} {}", self.table_key, set_value, self.make_clauses()),
-			&[(":value", &value)]
-		).map(|_|())
+		let set_value = format!("{} = json_set({}, ?, ?)", self.data_key, self.data_key);
+		self.update(&set_value, &[&path, &value], connection)
 	}
 
 	/// Deletes the entry.
@@ -367,20 +638,19 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// table.insert(12, &connection)?;
 	/// table.insert(3, &connection)?;
 	/// // We expect 4 entries
-	/// let length = table.iter().id(&connection)?.len();
+	/// let length = table.iter().count(&connection)?;
 	/// assert_eq!(length, 4);
 	/// // Now we'll remove every number 10 or above.
 	/// table.iter().filter(field("").gte(10)).delete(&connection)?;
 	/// // There should only be 2 entries left.
-	/// let length = table.iter().id(&connection)?.len();
+	/// let length = table.iter().count(&connection)?;
 	/// assert_eq!(length, 2);
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn delete<C: AsRef<SqliteConnection>>(&self, connection: C) -> SqliteResult<()> {
-		self.execute("DELETE",
-			|mut statement, params| statement.execute_named(&params),
-			connection
-		).map(|_|())
+	pub fn delete<C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<()> {
+		let clauses = self.query()?.mutation_clauses(self.table_key, self.id_key);
+		connection.as_ref().execute(&format!("DELETE FROM {} {}", self.table_key, clauses.sql), &clauses.params)?;
+		Ok(())
 	}
 
 	/// Applies a filter on what entries the command will operate on.
@@ -406,7 +676,7 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// 	// They should be 18+ years old
 	/// 	assert!(person.1 >= 18);
 	/// }
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
 	pub fn filter<A: Filter>(self, filter: A) -> Iterator<'a, I, A, S> {
 		Iterator {
@@ -417,6 +687,8 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 			offset: self.offset,
 			order_by: self.order_by,
 			table_key: self.table_key,
+			table_name: self.table_name,
+			generated: self.generated,
 			data_key: self.data_key,
 		}
 	}
@@ -440,7 +712,7 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// assert_eq!(data[1], 3);
 	/// assert_eq!(data[2], 6);
 	/// assert_eq!(data[3], 8);
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
 	///
 	/// ```
@@ -460,7 +732,7 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// assert!(data[1].0 == 2 && data[1].1 == 2);
 	/// assert!(data[2].0 == 2 && data[2].1 == 8);
 	/// assert!(data[3].0 == 8 && data[3].1 == 4);
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
 	pub fn sort<A: Sort>(self, key: A) -> Iterator<'a, I, W, A> {
 		Iterator {
@@ -472,6 +744,76 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 			offset: self.offset,
 			order_by: key,
 			table_key: self.table_key,
+			table_name: self.table_name,
+			generated: self.generated,
+		}
+	}
+
+	/// Only keep the entries that match a full-text search query.
+	///
+	/// The table must have a full-text index created using [`Table::search_index`].
+	/// The query uses the [FTS5 query syntax](https://www.sqlite.org/fts5.html#full_text_query_syntax).
+	/// This is added to any filter that is already being used.
+	///
+	/// [`Table::search_index`]: struct.Table.html#method.search_index
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("posts")?;
+	/// table.search_index(&["title", "body"], &connection)?;
+	/// table.insert(json!({"title": "Rust", "body": "Fearless concurrency", "likes": 10}), &connection)?;
+	/// table.insert(json!({"title": "Python", "body": "Batteries included", "likes": 3}), &connection)?;
+	/// table.insert(json!({"title": "Concurrency in Go", "body": "Goroutines", "likes": 1}), &connection)?;
+	/// let titles: Vec<String> = table.iter()
+	/// 	.filter(field("likes").gt(2))
+	/// 	.search("concurrency")
+	/// 	.field("title", &connection)?;
+	/// assert_eq!(titles, ["Rust"]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn search(self, query: &str) -> Iterator<'a, I, And<W, Search>, S> {
+		let search = self.search_query(query);
+		self.filter_with(search)
+	}
+
+	/// Only keep the entries that match a full-text search query, with the most relevant entries first.
+	///
+	/// Any sort that is already being used is only used for entries that are equally relevant.
+	/// See [`search`] for more details.
+	///
+	/// [`search`]: #method.search
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("posts")?;
+	/// table.search_index(&["title", "body"], &connection)?;
+	/// table.insert(json!({"title": "Cooking", "body": "Rust can grow on cast iron pans"}), &connection)?;
+	/// table.insert(json!({"title": "Rust", "body": "Rust is a systems programming language, Rust is fast"}), &connection)?;
+	/// table.insert(json!({"title": "Gardening", "body": "Tomatoes"}), &connection)?;
+	/// let titles: Vec<String> = table.iter().search_by_relevance("rust").field("title", &connection)?;
+	/// assert_eq!(titles, ["Rust", "Cooking"]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn search_by_relevance(self, query: &str) -> Iterator<'a, I, And<W, Search>, And<Search, S>> {
+		let search = self.search_query(query);
+		let iterator = self.filter_with(search.clone());
+		Iterator {
+			data_key: iterator.data_key,
+			where_: iterator.where_,
+			id_key: iterator.id_key,
+			id_type: iterator.id_type,
+			limit: iterator.limit,
+			offset: iterator.offset,
+			order_by: And { first: search, second: iterator.order_by },
+			table_key: iterator.table_key,
+			table_name: iterator.table_name,
+			generated: iterator.generated,
 		}
 	}
 
@@ -496,7 +838,7 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// assert_eq!(people.len(), 2);
 	/// assert_eq!(people[0], "Callum");
 	/// assert_eq!(people[1], "John");
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
 	pub fn skip(mut self, n: u32) -> Self {
 		self.offset = Some(n);
@@ -524,7 +866,7 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// assert_eq!(people.len(), 2);
 	/// assert_eq!(people[0], "Hiruna");
 	/// assert_eq!(people[1], "Bob");
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
 	pub fn take(mut self, n: u32) -> Self {
 		self.limit = Some(n);
@@ -534,49 +876,101 @@ impl<'a, I: FromSql, W: Filter, S: Sort> Iterator<'a, I, W, S> {
 	/// Execute a query using the given command (e.g. "SELECT data"),
 	/// the given function to handle the output, and the connection to the database.
 	///
+	/// The function is given the prepared statement and the values that must be bound to it.
+	///
 	/// *It is not recommended to use this method.*
-	pub fn execute<A, F, C>(&self, command: &str, execute: F, connection: C) -> SqliteResult<A>
+	pub fn execute<A, F, C>(&self, command: &str, execute: F, connection: C) -> Result<A>
 		where
-			F: FnOnce(Statement, Vec<(&str, &dyn ToSql)>) -> SqliteResult<A>,
+			F: FnOnce(Statement, Vec<Value>) -> Result<A>,
 			C: AsRef<SqliteConnection>,
 	{
-		let con = connection.as_ref().prepare(&format!("{} FROM {} {}", command, &self.table_key, self.make_clauses()))?;
-		let params = vec![];
-		execute(con, params)
-	}
-
-	fn make_clauses(&self) -> String {
-		let where_ = self.where_.where_(&self.data_key).map(|w| format!("WHERE {}", w)).unwrap_or_default();
-		let limit = if self.limit.is_none() && self.offset.is_none() { String::new() }
-		else { format!("LIMIT {} OFFSET {}", self.limit.map(|i| i as i64).unwrap_or(-1), self.offset.unwrap_or(0)) };
-		let order = self.order_by.order_by(&self.data_key);
-		let order = if order.is_empty() { String::new() } else {
-			let mut first_time = true;
-			order.into_iter()
-				.fold("ORDER BY ".to_string(), |mut string, key| {
-					if !first_time {
-						string.push(',');
-					}
-					first_time = false;
-					string.push_str(&key);
-					string
-				})
-		};
-		format!("{} {} {}", where_, limit, order)
+		let clauses = self.query()?.clauses();
+		let con = connection.as_ref().prepare(&format!("{} FROM {} {}", command, &self.table_key, clauses.sql))?;
+		execute(con, clauses.params)
+	}
+
+	/// Creates a query that selects the given columns from the entries that would be returned.
+	///
+	/// The entries are chosen in a sub-query so that skipping and taking apply to the entries
+	/// instead of the aggregated rows.
+	pub(crate) fn aggregate(&self, columns: &str, rest: &str) -> Result<Clause> {
+		let Clause { sql, params } = self.query()?.clauses();
+		Ok(Clause::new(format!("SELECT {} FROM (SELECT * FROM {} {}) {}", columns, self.table_key, sql, rest), params))
+	}
+
+	/// Aggregates the entries into a single value.
+	fn aggregate_one<T: FromSql, C: AsRef<SqliteConnection>>(&self, expression: &str, connection: C) -> Result<T> {
+		let Clause { sql, params } = self.aggregate(expression, "")?;
+		Ok(connection.as_ref().query_row(&sql, &params, |row| row.get(0))?)
+	}
+
+	/// Updates the entries using the given `SET` expression.
+	///
+	/// The values are bound to the parameters in the `SET` expression,
+	/// and come before the values bound by the filter.
+	fn update<C: AsRef<SqliteConnection>>(&self, set_value: &str, values: &[&dyn ToSql], connection: C) -> Result<()> {
+		let clauses = self.query()?.mutation_clauses(self.table_key, self.id_key);
+		let params = values.iter().copied().chain(clauses.params.iter().map(|p| p as &dyn ToSql));
+		let connection = connection.as_ref();
+		connection.execute(&format!("UPDATE {} SET {} {}", self.table_key, set_value, clauses.sql), params)
+			.map_err(|error| Error::from(error).with_index_fields(connection))?;
+		Ok(())
+	}
+
+	/// Creates a full-text search query using the table's full-text index.
+	fn search_query(&self, query: &str) -> Search {
+		Search {
+			index: quote(&search_index(self.table_name)),
+			table: self.table_key.into(),
+			query: query.into(),
+		}
+	}
+
+	/// Adds a filter to the one already being used.
+	fn filter_with<A: Filter>(self, filter: A) -> Iterator<'a, I, And<W, A>, S> {
+		let first = self.where_;
+		Iterator {
+			where_: And { first, second: filter },
+			id_key: self.id_key,
+			id_type: self.id_type,
+			limit: self.limit,
+			offset: self.offset,
+			order_by: self.order_by,
+			table_key: self.table_key,
+			table_name: self.table_name,
+			generated: self.generated,
+			data_key: self.data_key,
+		}
+	}
+
+	/// Creates the structured representation of the query's clauses.
+	pub(crate) fn query(&self) -> Result<Query> {
+		with_generated(self.data_key, &self.generated, || Ok(Query {
+			where_: self.where_.where_(self.data_key)?,
+			order_by: self.order_by.order_by(self.data_key),
+			limit: self.limit,
+			offset: self.offset,
+		}))
 	}
 }
 
-fn get_first_column<T, A, F>(map: F) -> impl Fn(Statement, Vec<(&str, &dyn ToSql)>) -> SqliteResult<Vec<T>>
+/// Gets the value in the second column of each row,
+/// using the id in the first column and the JSON path to report any errors.
+fn get_second_column<'p, T, A, F>(path: &'p str, map: F) -> impl Fn(Statement, Vec<Value>) -> Result<Vec<Result<T>>> + 'p
 where
 	A: FromSql,
-	F: Fn(A) -> T,
+	F: Fn(A) -> T + 'p,
 {
 	move |mut statement, params| {
-		Ok(statement.query_map_named(&params, |row| row.get(0))?
-			.filter_map(Result::ok)
-			.map(&map)
+		Ok(statement.query_and_then(&params, |row| second_column(row, path))?
+			.map(|value| value.map(&map))
 			.collect())
 	}
 }
 
+/// Gets the value in the second column of the row, attaching the id in the first column to any error.
+pub(crate) fn second_column<A: FromSql>(row: &Row, path: &str) -> Result<A> {
+	row.get(1).map_err(|error| Error::from(error).at(row.get(0).ok(), Some(path)))
+}
+
 fn no_map<T>(in_: T) -> T { in_ }
@@ -1,13 +1,17 @@
 use serde::Serialize;
-use serde_json::to_string;
 
-use crate::{SortOrder, util::{Gt, Gte, Eq, Exists, Like, Neq}};
+use crate::{quote, quote_literal, SortOrder, util::{Between, generated_for, Contains, Gt, Gte, Eq, Exists, In, IsMissing, IsNull, IsType, Like, Neq, NotIn, Param}};
 
 /// This can be used for filters or getting fields
 pub trait Key {
 	/// Produces the string that will be used by SQL.
 	fn key(&self, data_key: &str) -> String;
 
+	/// Produces the SQL expression for the JSON type of the key, if it is a field in a JSON object.
+	///
+	/// The expression should be `NULL` if the field doesn't exist.
+	fn json_type(&self, _data_key: &str) -> Option<String> { None }
+
 	/// Compares for equality.
 	///
 	/// # Example
@@ -28,11 +32,26 @@ pub trait Key {
 	/// assert_eq!(numbers.len(), 2);
 	/// // They both should be equal to 3
 	/// assert_eq!(numbers.into_iter().any(|number| number != 3), false);
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	///
+	/// The value is bound as a parameter so it is never interpreted as SQL.
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// table.insert(json!({"name": "O'Brien"}), &connection)?;
+	/// table.insert(json!({"name": "Bob"}), &connection)?;
+	/// let ids: Vec<i64> = table.iter().filter(field("name").eq("O'Brien")).id(&connection)?;
+	/// assert_eq!(ids, [1]);
+	/// let ids: Vec<i64> = table.iter().filter(field("name").eq("' OR 1=1 --")).id(&connection)?;
+	/// assert!(ids.is_empty());
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	fn eq<T: Serialize>(self, value: T) -> Eq<Self, String>
+	fn eq<T: Serialize>(self, value: T) -> Eq<Self, Param>
 		where Self: Sized {
-		Eq { variable: self, value: to_string(&value).unwrap() }
+		Eq { variable: self, value: Param::new(&value) }
 	}
 
 	/// Compares for inequality.
@@ -55,11 +74,11 @@ pub trait Key {
 	/// assert_eq!(numbers.len(), 3);
 	/// // They both should be equal to 3
 	/// assert!(!numbers.into_iter().any(|number| number == 3));
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	fn neq<T: Serialize>(self, value: T) -> Neq<Self, String>
+	fn neq<T: Serialize>(self, value: T) -> Neq<Self, Param>
 		where Self: Sized {
-		Neq { variable: self, value: to_string(&value).unwrap() }
+		Neq { variable: self, value: Param::new(&value) }
 	}
 	/// Compares if it is greater than the value.
 	///
@@ -80,11 +99,11 @@ pub trait Key {
 	/// // Only 9 is bigger than 4
 	/// assert_eq!(numbers.len(), 1);
 	/// assert!(numbers[0] > 4);
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	fn gt<T: Serialize>(self, value: T) -> Gt<Self, String>
+	fn gt<T: Serialize>(self, value: T) -> Gt<Self, Param>
 		where Self: Sized{
-		Gt { greater: self, lesser: to_string(&value).unwrap() }
+		Gt { greater: self, lesser: Param::new(&value) }
 	}
 	/// Compares if it is greater than or equal to the value.
 	///
@@ -104,11 +123,11 @@ pub trait Key {
 	/// 	.filter(field("number").gte(4)).field("number", &connection)?;
 	/// assert_eq!(numbers.len(), 2);
 	/// assert!(!numbers.into_iter().any(|number| number < 4));
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	fn gte<T: Serialize>(self, value: T) -> Gte<Self, String>
+	fn gte<T: Serialize>(self, value: T) -> Gte<Self, Param>
 		where Self: Sized {
-		Gte { greater: self, lesser: to_string(&value).unwrap() }
+		Gte { greater: self, lesser: Param::new(&value) }
 	}
 	/// Compares if it is less than the value.
 	///
@@ -128,11 +147,11 @@ pub trait Key {
 	/// 	.filter(field("number").lt(4)).field("number", &connection)?;
 	/// assert_eq!(numbers.len(), 3);
 	/// assert!(!numbers.into_iter().any(|number| number >= 4));
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	fn lt<T: Serialize>(self, value: T) -> Gt<String, Self>
+	fn lt<T: Serialize>(self, value: T) -> Gt<Param, Self>
 		where Self: Sized {
-		Gt { lesser: self, greater: to_string(&value).unwrap() }
+		Gt { lesser: self, greater: Param::new(&value) }
 	}
 	/// Compares if it is greater than or equal to the variable.
 	///
@@ -152,11 +171,11 @@ pub trait Key {
 	/// 	.filter(field("number").lte(4)).field("number", &connection)?;
 	/// assert_eq!(numbers.len(), 4);
 	/// assert!(!numbers.into_iter().any(|number| number > 4));
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	fn lte<T: Serialize>(self, value: T) -> Gte<String, Self>
+	fn lte<T: Serialize>(self, value: T) -> Gte<Param, Self>
 		where Self: Sized {
-		Gte { lesser: self, greater: to_string(&value).unwrap() }
+		Gte { lesser: self, greater: Param::new(&value) }
 	}
 	/// Uses the SQL like comparison operator.
 	///
@@ -183,7 +202,7 @@ pub trait Key {
 	/// 	.filter(field("name").like(false, "H%runa", false)).field("name", &connection)?;
 	/// // We should only match two names
 	/// assert_eq!(names.len(), 2);
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
 	fn like<S: std::fmt::Display>(self, matches_start: bool, value: S, matches_end: bool) -> Like<Self, S>
 		where Self: Sized {
@@ -210,10 +229,156 @@ pub trait Key {
 	/// assert_eq!(ids.len(), 2);
 	/// assert_eq!(ids[0], 1);
 	/// assert_eq!(ids[1], 3);
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
 	fn exists(self) -> Exists<Self> where Self: Sized { Exists(self) }
 
+	/// Compares whether it is equal to any of the values.
+	///
+	/// The values are bound as a single JSON array so any number of values can be used.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// table.insert(json!({"name": "Hiruna"}), &connection)?;
+	/// table.insert(json!({"name": "Bob"}), &connection)?;
+	/// table.insert(json!({"name": "Callum"}), &connection)?;
+	/// let ids: Vec<i64> = table.iter().filter(field("name").in_(&["Bob", "Callum", "Alex"])).id(&connection)?;
+	/// assert_eq!(ids, [2, 3]);
+	/// // Large sets of values can be used
+	/// let count = table.iter().filter(column("id").in_(0..10_000)).count(&connection)?;
+	/// assert_eq!(count, 3);
+	/// # use nosqlite::column;
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	fn in_<I, T>(self, values: I) -> In<Self>
+	where
+		Self: Sized,
+		I: IntoIterator<Item=T>,
+		T: Serialize,
+	{
+		In { variable: self, values: Param::new(&values.into_iter().collect::<Vec<_>>()) }
+	}
+
+	/// Compares whether it is not equal to any of the values.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// table.insert(json!({"name": "Hiruna"}), &connection)?;
+	/// table.insert(json!({"name": "Bob"}), &connection)?;
+	/// table.insert(json!({"name": "Callum"}), &connection)?;
+	/// let names: Vec<String> = table.iter().filter(field("name").not_in(&["Bob", "Callum"])).field("name", &connection)?;
+	/// assert_eq!(names, ["Hiruna"]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	fn not_in<I, T>(self, values: I) -> NotIn<Self>
+	where
+		Self: Sized,
+		I: IntoIterator<Item=T>,
+		T: Serialize,
+	{
+		NotIn { variable: self, values: Param::new(&values.into_iter().collect::<Vec<_>>()) }
+	}
+
+	/// Compares whether it is between the two values, including the values themselves.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// for number in 0..10 {
+	/// 	table.insert(json!({"number": number}), &connection)?;
+	/// }
+	/// let numbers: Vec<u8> = table.iter().filter(field("number").between(3, 5)).field("number", &connection)?;
+	/// assert_eq!(numbers, [3, 4, 5]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	fn between<T: Serialize>(self, low: T, high: T) -> Between<Self>
+		where Self: Sized {
+		Between { variable: self, low: Param::new(&low), high: Param::new(&high) }
+	}
+
+	/// Whether the field exists in the JSON object and is null.
+	///
+	/// For keys that aren't fields in a JSON object, this checks whether the value is `NULL`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// table.insert(json!({"a": null}), &connection)?;
+	/// table.insert(json!({"b": 3}), &connection)?;
+	/// table.insert(json!({"a": 3}), &connection)?;
+	/// let ids: Vec<i64> = table.iter().filter(field("a").is_null()).id(&connection)?;
+	/// assert_eq!(ids, [1]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	#[allow(clippy::wrong_self_convention)]
+	fn is_null(self) -> IsNull<Self> where Self: Sized { IsNull(self) }
+
+	/// Whether the field does not exist in the JSON object.
+	///
+	/// Unlike [`is_null`], a field that exists but is null does not match.
+	/// Keys that aren't fields in a JSON object are never missing.
+	///
+	/// [`is_null`]: #method.is_null
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// table.insert(json!({"a": null}), &connection)?;
+	/// table.insert(json!({"b": 3}), &connection)?;
+	/// table.insert(json!({"a": 3}), &connection)?;
+	/// let ids: Vec<i64> = table.iter().filter(field("a").is_missing()).id(&connection)?;
+	/// assert_eq!(ids, [2]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	#[allow(clippy::wrong_self_convention)]
+	fn is_missing(self) -> IsMissing<Self> where Self: Sized { IsMissing(self) }
+
+	/// Whether the value has the given JSON type.
+	///
+	/// For keys that aren't fields in a JSON object, only the types
+	/// [`Integer`], [`Real`], [`Text`] and [`Null`] can match.
+	///
+	/// [`Integer`]: enum.JsonType.html#variant.Integer
+	/// [`Real`]: enum.JsonType.html#variant.Real
+	/// [`Text`]: enum.JsonType.html#variant.Text
+	/// [`Null`]: enum.JsonType.html#variant.Null
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, Filter, json, JsonType, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna", "age": 19}), &connection)?;
+	/// table.insert(json!({"name": "Bob", "age": "13"}), &connection)?;
+	/// table.insert(json!({"name": "Callum", "age": 25.5}), &connection)?;
+	/// // Find the people whose age is not a number
+	/// let names: Vec<String> = table.iter()
+	/// 	.filter(field("age").is_type(JsonType::Integer).or(field("age").is_type(JsonType::Real)).not())
+	/// 	.field("name", &connection)?;
+	/// assert_eq!(names, ["Bob"]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	#[allow(clippy::wrong_self_convention)]
+	fn is_type(self, json_type: JsonType) -> IsType<Self> where Self: Sized { IsType { variable: self, json_type } }
+
 	/// This field is to be sorted in ascending order.
 	///
 	/// # Example
@@ -231,7 +396,7 @@ pub trait Key {
 	/// assert_eq!(a[0], 4);
 	/// assert_eq!(a[1], 6);
 	/// assert_eq!(a[2], 8);
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
 	fn ascending(self) -> SortOrder<Self> where Self: Sized { SortOrder::Ascending(self) }
 
@@ -252,12 +417,49 @@ pub trait Key {
 	/// assert_eq!(a[0], 8);
 	/// assert_eq!(a[1], 6);
 	/// assert_eq!(a[2], 4);
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
 	fn descending(self) -> SortOrder<Self> where Self: Sized { SortOrder::Descending(self) }
 }
 impl<K: Key + ?Sized> Key for &K {
 	fn key(&self, data_key: &str) -> String { (*self).key(data_key) }
+	fn json_type(&self, data_key: &str) -> Option<String> { (*self).json_type(data_key) }
+}
+
+/// The types of values in a JSON object, as named by SQLite's `json_type` function.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum JsonType {
+	/// `null`
+	Null,
+	/// `true`
+	True,
+	/// `false`
+	False,
+	/// A number without a fractional part.
+	Integer,
+	/// A number with a fractional part.
+	Real,
+	/// A string.
+	Text,
+	/// An array.
+	Array,
+	/// An object.
+	Object,
+}
+impl JsonType {
+	/// The name that SQLite uses for the type.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			JsonType::Null => "null",
+			JsonType::True => "true",
+			JsonType::False => "false",
+			JsonType::Integer => "integer",
+			JsonType::Real => "real",
+			JsonType::Text => "text",
+			JsonType::Array => "array",
+			JsonType::Object => "object",
+		}
+	}
 }
 
 /// Formats the JSON field key into a path so that it can be used with the extension.
@@ -296,15 +498,136 @@ pub struct Field(pub String);
 ///
 /// If the string is empty, the root is assumed.
 pub fn field(field: &str) -> Field { Field(format_key(field)) }
+impl Field {
+	/// Whether the field is a JSON array that contains the value.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, Filter, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("posts")?;
+	/// table.insert(json!({"title": "Crates", "tags": ["rust", "cargo"]}), &connection)?;
+	/// table.insert(json!({"title": "Queries", "tags": ["sql"]}), &connection)?;
+	/// table.insert(json!({"title": "Untagged"}), &connection)?;
+	/// let titles: Vec<String> = table.iter().filter(field("tags").contains("rust")).field("title", &connection)?;
+	/// assert_eq!(titles, ["Crates"]);
+	/// // It can be used with other filters
+	/// let titles: Vec<String> = table.iter()
+	/// 	.filter(field("tags").contains("rust").not())
+	/// 	.field("title", &connection)?;
+	/// assert_eq!(titles, ["Queries", "Untagged"]);
+	/// // A field that isn't an array doesn't contain anything, even if it is equal to the value
+	/// table.insert(json!({"title": "Scalar", "tags": "rust"}), &connection)?;
+	/// let titles: Vec<String> = table.iter().filter(field("tags").contains("rust")).field("title", &connection)?;
+	/// assert_eq!(titles, ["Crates"]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn contains<T: Serialize>(self, value: T) -> Contains {
+		Contains { path: self.0, values: vec![Param::new(&value)], all: true }
+	}
+
+	/// Whether the field is a JSON array that contains at least one of the values.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("posts")?;
+	/// table.insert(json!({"title": "Crates", "tags": ["rust", "cargo"]}), &connection)?;
+	/// table.insert(json!({"title": "Queries", "tags": ["sql"]}), &connection)?;
+	/// table.insert(json!({"title": "Scripts", "tags": ["python"]}), &connection)?;
+	/// let titles: Vec<String> = table.iter()
+	/// 	.filter(field("tags").contains_any(&["cargo", "sql"]))
+	/// 	.field("title", &connection)?;
+	/// assert_eq!(titles, ["Crates", "Queries"]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn contains_any<I, T>(self, values: I) -> Contains
+	where
+		I: IntoIterator<Item=T>,
+		T: Serialize,
+	{
+		Contains { path: self.0, values: values.into_iter().map(|value| Param::new(&value)).collect(), all: false }
+	}
+
+	/// Whether the field is a JSON array that contains all of the values.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("posts")?;
+	/// table.insert(json!({"title": "Crates", "tags": ["rust", "cargo"]}), &connection)?;
+	/// table.insert(json!({"title": "Traits", "tags": ["rust"]}), &connection)?;
+	/// let titles: Vec<String> = table.iter()
+	/// 	.filter(field("tags").contains_all(&["cargo", "rust"]))
+	/// 	.field("title", &connection)?;
+	/// assert_eq!(titles, ["Crates"]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn contains_all<I, T>(self, values: I) -> Contains
+	where
+		I: IntoIterator<Item=T>,
+		T: Serialize,
+	{
+		Contains { path: self.0, values: values.into_iter().map(|value| Param::new(&value)).collect(), all: true }
+	}
+
+	/// The length of the field if it is a JSON array.
+	///
+	/// This is a [`Key`] so it can be compared or sorted like any other key.
+	/// The length is `0` if the field is not an array and `NULL` if it doesn't exist.
+	///
+	/// [`Key`]: trait.Key.html
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("posts")?;
+	/// table.insert(json!({"title": "Crates", "tags": ["rust", "cargo"]}), &connection)?;
+	/// table.insert(json!({"title": "Queries", "tags": ["sql"]}), &connection)?;
+	/// table.insert(json!({"title": "Untagged", "tags": []}), &connection)?;
+	/// let titles: Vec<String> = table.iter()
+	/// 	.filter(field("tags").array_len().gte(1))
+	/// 	.sort(field("tags").array_len().descending())
+	/// 	.field("title", &connection)?;
+	/// assert_eq!(titles, ["Crates", "Queries"]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn array_len(self) -> ArrayLength { ArrayLength(self) }
+}
 impl Key for Field {
 	fn key(&self, data_key: &str) -> String {
-		format!("json_extract({}, \"{}\")", data_key, self.0)
+		// Filters and sorts use the generated column that holds the field if there is one, so that its index is used
+		generated_for(data_key, &self.0)
+			.unwrap_or_else(|| format!("json_extract({}, {})", data_key, quote_literal(&self.0)))
+	}
+	fn json_type(&self, data_key: &str) -> Option<String> {
+		Some(format!("json_type({}, {})", data_key, quote_literal(&self.0)))
+	}
+}
+
+/// The length of a JSON array in a field.
+///
+/// Create this using [`Field::array_len`].
+///
+/// [`Field::array_len`]: struct.Field.html#method.array_len
+pub struct ArrayLength(pub Field);
+impl Key for ArrayLength {
+	fn key(&self, data_key: &str) -> String {
+		format!("json_array_length({}, {})", data_key, quote_literal(&(self.0).0))
 	}
 }
 
 /// A column in the SQL table.
 ///
 /// Create this using the [`column`] function.
+/// The name of the column is always quoted when it is used.
 ///
 /// This isn't generally used unless you have a custom table.
 ///
@@ -322,7 +645,7 @@ impl Key for Field {
 /// // Only 2 entries should have been queried
 /// assert_eq!(data.len(), 2);
 /// assert_eq!(data.into_iter().any(|id| id == 1), false);
-/// # rusqlite::Result::Ok(())
+/// # nosqlite::Result::Ok(())
 /// ```
 ///
 /// [`column`]: fn.column.html
@@ -330,5 +653,5 @@ pub struct Column(pub String);
 /// Create a representation of a column in a SQL table.
 pub fn column<S: Into<String>>(column: S) -> Column { Column(column.into()) }
 impl Key for Column {
-	fn key(&self, _: &str) -> String { self.0.clone() }
+	fn key(&self, _: &str) -> String { quote(&self.0) }
 }
@@ -12,14 +12,47 @@ use rusqlite::{Connection as SqliteConnection, Error as SqliteError, NO_PARAMS,
 use serde::{Deserialize, de::DeserializeOwned, Serialize};
 use serde_json::to_string;
 
-use std::{marker::{PhantomData, Sized}, path::Path};
+use std::{cell::RefCell, marker::Sized, path::Path, sync::{Arc, mpsc::Receiver, Mutex}};
 
+#[cfg(feature = "tokio")]
+mod asynchronous;
+#[cfg(feature = "tokio")]
+pub use asynchronous::{AsyncConnection, AsyncStream};
+mod builder;
+pub use builder::{ConnectionBuilder, JournalMode, Synchronous};
+mod changes;
+pub use changes::{Change, ChangeEvent, ChangeOp};
+pub(crate) use changes::Changes;
+mod collection;
+pub use collection::{Collection, TypedIterator};
+mod companion;
+mod error;
+pub use error::{Error, Result};
+pub(crate) use error::{id_value, index_fields};
+mod fields;
+pub use fields::{Comparable, Fields, TypedField};
+#[cfg(feature = "derive")]
+pub use nosqlite_derive::Fields;
+mod group;
+pub use group::GroupBy;
+mod identifier;
+pub use identifier::Identifier;
+pub(crate) use identifier::{quote, quote_literal};
 mod iterator;
 pub use iterator::Iterator;
+pub(crate) use iterator::second_column;
 mod key;
-pub use key::{column, Column, field, Field, format_key, Key};
+pub use key::{ArrayLength, column, Column, field, Field, format_key, JsonType, Key};
+mod pool;
+pub use pool::{Pool, PooledConnection};
+mod query;
+pub use query::Query;
+mod stream;
+pub use stream::{Rows, Stream};
 mod table;
-pub use table::{KeyTable, Operation, Table};
+pub use table::{Index, KeyTable, Operation, Table};
+mod transaction;
+pub use transaction::Transaction;
 pub mod util;
 pub(crate) use util::*;
 
@@ -29,6 +62,8 @@ pub use serde_json::json;
 /// A connection the underlying sqlite database.
 pub struct Connection {
 	connection: SqliteConnection,
+	/// The changes that are waiting to be committed, once something has subscribed to them.
+	changes: RefCell<Option<Arc<Mutex<Changes>>>>,
 }
 impl Connection {
 	/// Opens a connection to a sqlite database.
@@ -40,10 +75,10 @@ impl Connection {
 	/// ```no_run
 	/// use nosqlite::Connection;
 	/// let connection = Connection::open("database.db")?;
-	/// # Ok::<(), rusqlite::Error>(())
+	/// # Ok::<(), nosqlite::Error>(())
 	/// ```
-	pub fn open<P: AsRef<Path>>(path: P) -> SqliteResult<Self> {
-		Ok(Self { connection: SqliteConnection::open(path)? })
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+		Ok(Self::new(SqliteConnection::open(path)?))
 	}
 
 	/// Opens a new connection to a sqlite database in-memory.
@@ -53,12 +88,24 @@ impl Connection {
 	/// ```rust
 	/// use nosqlite::Connection;
 	/// let connection = Connection::in_memory()?;
-	/// # Ok::<(), rusqlite::Error>(())
+	/// # Ok::<(), nosqlite::Error>(())
 	/// ```
-	pub fn in_memory() -> SqliteResult<Self> {
-		Ok(Self { connection: SqliteConnection::open_in_memory()? })
+	pub fn in_memory() -> Result<Self> {
+		Ok(Self::new(SqliteConnection::open_in_memory()?))
 	}
 
+	/// Wraps a connection that has already been opened.
+	pub(crate) fn new(connection: SqliteConnection) -> Self {
+		Self { connection, changes: RefCell::new(None) }
+	}
+
+	/// Creates a builder to open a connection with options, such as SQLite's pragmas.
+	///
+	/// See [`ConnectionBuilder`] for more details.
+	///
+	/// [`ConnectionBuilder`]: struct.ConnectionBuilder.html
+	pub fn builder() -> ConnectionBuilder { ConnectionBuilder::default() }
+
 	/// Gets a table in the database using its name.
 	///
 	/// Creates one if it doesn't exist.
@@ -69,22 +116,19 @@ impl Connection {
 	/// # use nosqlite::{Connection, Table};
 	/// # let connection = Connection::in_memory()?;
 	/// let table = connection.table("people")?;
-	/// # Ok::<(), rusqlite::Error>(())
+	/// # Ok::<(), nosqlite::Error>(())
 	/// ```
-	pub fn table<T: Into<String>>(&self, table: T) -> SqliteResult<Table<i64>> {
-		let table = table.into();
+	pub fn table<T: Into<String>>(&self, table: T) -> Result<Table<i64>> {
+		let table = Table::new(table)?;
 		self.connection.execute(&format!(r#"
 			CREATE TABLE IF NOT EXISTS {} (
-				id INTEGER PRIMARY KEY,
-				data TEXT NOT NULL
+				{} INTEGER PRIMARY KEY,
+				{} TEXT NOT NULL
 			)
-		"#, table), NO_PARAMS)
-			.map(move |_| Table {
-				id: "id".into(),
-				id_type: PhantomData::default(),
-				data: "data".into(),
-				name: table,
-			})
+		"#, table.name, table.id, table.data), NO_PARAMS)?;
+		let table = Table { rowid_id: true, ..table };
+		table.find_generated(&self.connection)?;
+		Ok(table)
 	}
 
 	/// gets a keyed table in the database using its name.
@@ -98,22 +142,243 @@ impl Connection {
 	/// let table: KeyTable<String> = connection.key_table("test".to_string())?;
 	/// table.insert("Point".into(), json!({ "x": 3, "y": 10 }), &connection)?;
 	/// assert_eq!(table.as_ref().get("Point".into()).field("x", &connection)?, Some(3));
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn key_table<I: SqlType, T: Into<String>>(&self, table: T) -> SqliteResult<KeyTable<I>> {
-		let table = table.into();
+	pub fn key_table<I: SqlType, T: Into<String>>(&self, table: T) -> Result<KeyTable<I>> {
+		let table = Table::new(table)?;
 		self.connection.execute(&format!(r#"
 			CREATE TABLE IF NOT EXISTS {} (
-				id {} PRIMARY KEY,
-				data TEXT NOT NULL
+				{} {} PRIMARY KEY,
+				{} TEXT NOT NULL
 			)
-		"#, table, I::sql_type()), NO_PARAMS)
-			.map(move |_| KeyTable(Table {
-				id: "id".into(),
-				id_type: PhantomData::default(),
-				data: "data".into(),
-				name: table,
-			}))
+		"#, table.name, table.id, I::sql_type(), table.data), NO_PARAMS)?;
+		// Only an INTEGER PRIMARY KEY is an alias of the rowid
+		let table = Table { rowid_id: I::sql_type() == "INTEGER", ..table };
+		table.find_generated(&self.connection)?;
+		Ok(KeyTable(table))
+	}
+
+	/// Gets a table in the database that only stores JSON objects of one type.
+	///
+	/// Creates one if it doesn't exist.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// # use nosqlite::{Collection, Connection};
+	/// # use serde::{Deserialize, Serialize};
+	/// # let connection = Connection::in_memory()?;
+	/// #[derive(Deserialize, Serialize)]
+	/// struct Person {
+	/// 	name: String,
+	/// }
+	/// let people = connection.collection::<Person>("people")?;
+	/// people.insert(&Person { name: "Hiruna".into() }, &connection)?;
+	/// let names: Vec<String> = people.iter().data(&connection)?.into_iter().map(|person| person.name).collect();
+	/// assert_eq!(names, ["Hiruna"]);
+	/// # Ok::<(), nosqlite::Error>(())
+	/// ```
+	pub fn collection<D>(&self, collection: impl Into<String>) -> Result<Collection<D>> {
+		Ok(Collection::new(self.table(collection)?))
+	}
+
+	/// Lists the names of the tables in the database.
+	///
+	/// The tables used for full-text search indexes and changelogs, and the table that records them, aren't included.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::Connection;
+	/// # let connection = Connection::in_memory()?;
+	/// let posts = connection.table("posts")?;
+	/// posts.search_index(&["title"], &connection)?;
+	/// posts.changelog(&connection)?;
+	/// connection.table("people")?;
+	/// // Tables that only look like they are used by another table are included
+	/// connection.table("saved_search")?;
+	/// connection.table("audit_changelog")?;
+	/// assert_eq!(connection.tables()?, ["audit_changelog", "people", "posts", "saved_search"]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn tables(&self) -> Result<Vec<String>> {
+		let internal = companion::internal_tables(&self.connection)?;
+		let mut statement = self.connection.prepare(
+			r"SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite\_%' ESCAPE '\' ORDER BY name")?;
+		let tables = statement.query_map(NO_PARAMS, |row| row.get(0))?
+			.filter(|table| table.as_ref().map_or(true, |table| !internal.contains(table)))
+			.collect::<SqliteResult<_>>()?;
+		Ok(tables)
+	}
+
+	/// Drops a table along with its full-text search index and changelog, if they exist.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::Connection;
+	/// # let connection = Connection::in_memory()?;
+	/// let table = connection.table("people")?;
+	/// connection.drop_table("people")?;
+	/// assert!(connection.tables()?.is_empty());
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn drop_table<T: Into<String>>(&self, table: T) -> Result<()> {
+		let table = Identifier::new(table)?;
+		Transaction::atomic(&self.connection, |connection| {
+			companion::remove(connection, &table)?;
+			Ok(connection.execute_batch(&format!("DROP TABLE IF EXISTS {}", table))?)
+		})
+	}
+
+	/// Renames a table along with its full-text search index and changelog, the triggers that keep them up to date
+	/// and the indexes on its generated columns.
+	///
+	/// Any [`Table`] that uses the old name needs to be recreated with the new name.
+	///
+	/// [`Table`]: struct.Table.html
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json};
+	/// # let connection = Connection::in_memory()?;
+	/// let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna"}), &connection)?;
+	/// table.search_index(&["name"], &connection)?;
+	/// table.generated_column("name", field("name"), "TEXT", false, &connection)?;
+	/// table.changelog(&connection)?;
+	/// connection.rename_table("people", "users")?;
+	/// let table = connection.table("users")?;
+	/// table.changelog(&connection)?;
+	/// table.insert(json!({"name": "Bob"}), &connection)?;
+	/// assert_eq!(table.iter().search("bob").id(&connection)?, [2]);
+	/// assert_eq!(table.changes_since(0, &connection)?.len(), 1);
+	/// assert_eq!(connection.tables()?, ["users"]);
+	/// assert_eq!(table.indexes(&connection)?[0].name, "users_name");
+	/// // The old name can be used again
+	/// let people = connection.table("people")?;
+	/// people.search_index(&["name"], &connection)?;
+	/// people.generated_column("name", field("name"), "TEXT", false, &connection)?;
+	/// people.changelog(&connection)?;
+	/// people.insert(json!({"name": "Bob"}), &connection)?;
+	/// assert_eq!(people.iter().search("bob").id(&connection)?, [1]);
+	/// assert_eq!(people.changes_since(0, &connection)?.len(), 1);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn rename_table<F: Into<String>, T: Into<String>>(&self, from: F, to: T) -> Result<()> {
+		let (from, to) = (Identifier::new(from)?, Identifier::new(to)?);
+		Transaction::atomic(&self.connection, |connection| {
+			connection.execute_batch(&format!("ALTER TABLE {} RENAME TO {}", from, to))?;
+			companion::rename(connection, &from, &to)?;
+			// The indexes on generated columns are named after the table
+			let mut statement = connection.prepare("SELECT name FROM pragma_table_xinfo(?) WHERE hidden IN (2, 3)")?;
+			let columns = statement.query_map([to.as_str()], |row| row.get(0))?
+				.collect::<SqliteResult<Vec<String>>>()?;
+			for column in columns {
+				let index = generated_index(from.as_str(), &column);
+				let indexed: bool = connection.query_row(
+					"SELECT count(*) FROM sqlite_master WHERE type = 'index' AND name = ? AND tbl_name = ?",
+					[index.as_str(), to.as_str()],
+					|row| row.get(0),
+				)?;
+				if indexed {
+					connection.execute_batch(&format!("DROP INDEX {}; CREATE INDEX {} ON {} ({});",
+						quote(&index), Identifier::new(generated_index(to.as_str(), &column))?, to, quote(&column)))?;
+				}
+			}
+			Ok(())
+		})
+	}
+}
+impl Connection {
+	/// Receives the changes made to every table using this connection, once they have been committed.
+	///
+	/// Changes that are rolled back aren't received, and neither are changes made using other connections.
+	/// Only the rowid of each entry is known so it needs to be read again to get its JSON object.
+	/// Changes to the tables that belong to another table, such as a full-text search index or changelog,
+	/// aren't received if they already existed when subscribing.
+	///
+	/// The changes are sent by SQLite's commit hook, which runs just before the commit finishes,
+	/// so they are still received if the commit then fails, such as when the database is busy.
+	/// SQLite doesn't say when a savepoint is rolled back,
+	/// so the changes inside of one are received if the transaction around it is committed.
+	///
+	/// This sets the update, commit and rollback hooks of the connection, which belong to this crate from then on.
+	/// Setting any of them on the underlying connection stops the changes from being received.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{ChangeEvent, ChangeOp, Connection, Error, json};
+	/// # use std::collections::HashMap;
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.search_index(&["name"], &connection)?;
+	/// let changes = connection.subscribe()?;
+	/// connection.transaction(|tx| {
+	/// 	table.insert(json!({"name": "Hiruna"}), tx)?;
+	/// 	table.get(1).set("age", 19, tx)
+	/// })?;
+	/// // Nothing is received from a transaction that is rolled back
+	/// let result = connection.transaction(|tx| {
+	/// 	table.delete(1, tx)?;
+	/// 	// JSON objects can only have strings as keys
+	/// 	table.insert(HashMap::from([((1, 2), 3)]), tx)
+	/// });
+	/// assert!(matches!(result, Err(Error::Serialization(_))));
+	/// // The full-text search index isn't received but tables with similar names are
+	/// connection.table("people_search_history")?.insert(json!({"query": "Hiruna"}), &connection)?;
+	/// let received: Vec<ChangeEvent> = changes.try_iter().collect();
+	/// assert_eq!(received, [
+	/// 	ChangeEvent { table: "people".into(), op: ChangeOp::Insert, id: 1 },
+	/// 	ChangeEvent { table: "people".into(), op: ChangeOp::Update, id: 1 },
+	/// 	ChangeEvent { table: "people_search_history".into(), op: ChangeOp::Insert, id: 1 },
+	/// ]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn subscribe(&self) -> Result<Receiver<ChangeEvent>> {
+		let internal = companion::internal_tables(&self.connection)?;
+		Ok(Changes::subscribe(self, None, internal))
+	}
+
+	/// Runs the closure inside of a transaction.
+	///
+	/// The transaction is committed if the closure returns `Ok`
+	/// and rolled back if it returns `Err` or panics.
+	/// Use [`Transaction::savepoint`] to nest transactions.
+	///
+	/// [`Transaction::savepoint`]: struct.Transaction.html#method.savepoint
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, Error, json};
+	/// # use std::collections::HashMap;
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// connection.transaction(|tx| {
+	/// 	table.insert(json!(1), tx)?;
+	/// 	table.insert(json!(2), tx)
+	/// })?;
+	/// assert_eq!(table.iter().count(&connection)?, 2);
+	///
+	/// // Nothing is inserted if the transaction fails
+	/// let result = connection.transaction(|tx| {
+	/// 	table.insert(json!(3), tx)?;
+	/// 	// JSON objects can only have strings as keys
+	/// 	table.insert(HashMap::from([((1, 2), 3)]), tx)
+	/// });
+	/// assert!(matches!(result, Err(Error::Serialization(_))));
+	/// assert_eq!(table.iter().count(&connection)?, 2);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn transaction<T, E, F>(&self, f: F) -> std::result::Result<T, E>
+	where
+		E: From<Error>,
+		F: FnOnce(&Transaction) -> std::result::Result<T, E>,
+	{
+		Transaction::run(&self.connection, f)
 	}
 }
 impl AsRef<SqliteConnection> for Connection {
@@ -124,8 +389,9 @@ impl AsRef<SqliteConnection> for Connection {
 
 /// Represents a condition which will determine what entries the operation can work on.
 pub trait Filter {
-	/// Returns a string formatted for use in an SQL statement.
-	fn where_(&self, _: &str) -> Option<String>;
+	/// Returns a SQL expression formatted for use in an SQL statement,
+	/// along with the values that should be bound to its parameters.
+	fn where_(&self, _: &str) -> Result<Option<Clause>>;
 	/// Allows chaining of multiple conditions.
 	fn and<B: Filter>(self, second: B) -> And<Self, B>
 	where Self: std::marker::Sized
@@ -155,76 +421,159 @@ pub trait Filter {
 	/// let numbers: Vec<u8> = table.iter().filter(field("").eq(1).not()).data(&connection)?;
 	/// assert_eq!(numbers.len(), 2);
 	/// assert!(!numbers.into_iter().any(|number| number == 1));
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
 	fn not(self) -> Not<Self> where Self: Sized { Not(self) }
 }
 impl Filter for () {
-	fn where_(&self, _: &str) -> Option<String> { None }
+	fn where_(&self, _: &str) -> Result<Option<Clause>> { Ok(None) }
+}
+impl<F: Filter> Filter for Option<F> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		self.as_ref().map_or(Ok(None), |filter| filter.where_(data_key))
+	}
 }
 impl Filter for String {
-	fn where_(&self, _: &str) -> Option<String> { Some(self.clone()) }
+	fn where_(&self, _: &str) -> Result<Option<Clause>> { Ok(Some(self.clone().into())) }
+}
+impl Filter for Clause {
+	fn where_(&self, _: &str) -> Result<Option<Clause>> { Ok(Some(Clause::new(self.sql.clone(), self.params.clone()))) }
 }
 impl<A: Filter, B: Filter> Filter for And<A, B> {
-	fn where_(&self, data_key: &str) -> Option<String> {
-		Some(format!("({} AND {})",
-			self.first.where_(data_key).unwrap_or_default(),
-			self.second.where_(data_key).unwrap_or_default()))
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(join(self.first.where_(data_key)?, "AND", self.second.where_(data_key)?))
 	}
 }
 impl<A: Filter, B: Filter> Filter for Or<A, B> {
-	fn where_(&self, data_key: &str) -> Option<String> {
-		Some(format!("({} OR {})",
-			self.first.where_(data_key).unwrap_or_default(),
-			self.second.where_(data_key).unwrap_or_default()))
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(join(self.first.where_(data_key)?, "OR", self.second.where_(data_key)?))
 	}
 }
 impl<A: Filter> Filter for Not<A> {
-	fn where_(&self, data_key: &str) -> Option<String> {
-		Some(format!("NOT ({})", self.0.where_(data_key).unwrap_or_default()))
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(self.0.where_(data_key)?.map(|Clause { sql, params }| Clause::new(format!("NOT ({})", sql), params)))
 	}
 }
-impl<K: Key> Filter for Eq<K, String> {
-	fn where_(&self, data_key: &str) -> Option<String> {
-		Some(format!("{} = {}", self.variable.key(data_key), self.value))
+impl<K: Key> Filter for Eq<K, Param> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(Some(Clause::new(format!("{} = ?", self.variable.key(data_key)), vec![self.value.value()?])))
 	}
 }
-impl<K: Key> Filter for Neq<K, String> {
-	fn where_(&self, data_key: &str) ->Option<String> {
-		Some(format!("{} != {}", self.variable.key(data_key), self.value))
+impl<K: Key> Filter for Neq<K, Param> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(Some(Clause::new(format!("{} != ?", self.variable.key(data_key)), vec![self.value.value()?])))
 	}
 }
-impl<K: Key> Filter for Gt<K, String> {
-	fn where_(&self, data_key: &str) -> Option<String> {
-		Some(format!("{} > {}", self.greater.key(data_key), self.lesser))
+impl<K: Key> Filter for Gt<K, Param> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(Some(Clause::new(format!("{} > ?", self.greater.key(data_key)), vec![self.lesser.value()?])))
 	}
 }
-impl<K: Key> Filter for Gte<K, String> {
-	fn where_(&self, data_key: &str) -> Option<String> {
-		Some(format!("{} >= {}", self.greater.key(data_key), self.lesser))
+impl<K: Key> Filter for Gte<K, Param> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(Some(Clause::new(format!("{} >= ?", self.greater.key(data_key)), vec![self.lesser.value()?])))
 	}
 }
-impl<K: Key> Filter for Gt<String, K> {
-	fn where_(&self, data_key: &str) -> Option<String> {
-		Some(format!("{} < {}", self.lesser.key(data_key), self.greater))
+impl<K: Key> Filter for Gt<Param, K> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(Some(Clause::new(format!("{} < ?", self.lesser.key(data_key)), vec![self.greater.value()?])))
 	}
 }
-impl<K: Key> Filter for Gte<String, K> {
-	fn where_(&self, data_key: &str) -> Option<String> {
-		Some(format!("{} <= {}", self.lesser.key(data_key), self.greater))
+impl<K: Key> Filter for Gte<Param, K> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(Some(Clause::new(format!("{} <= ?", self.lesser.key(data_key)), vec![self.greater.value()?])))
 	}
 }
 impl<K: Key, S: std::fmt::Display> Filter for Like<K, S> {
-	fn where_(&self, data_key: &str) -> Option<String> {
-		Some(format!("{} LIKE '{}{}{}'", self.variable.key(data_key),
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		let pattern = format!("{}{}{}",
 			if self.matches_start { "%" } else { "" },
-		    self.value,
-		    if self.matches_end { "%" } else { "" }))
+			self.value,
+			if self.matches_end { "%" } else { "" });
+		Ok(Some(Clause::new(format!("{} LIKE ?", self.variable.key(data_key)), vec![Value::Text(pattern)])))
 	}
 }
 impl<A: Key> Filter for Exists<A> {
-	fn where_(&self, data_key: &str) -> Option<String> {
-		Some(format!("{} IS NOT NULL", self.0.key(data_key)))
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(Some(format!("{} IS NOT NULL", self.0.key(data_key)).into()))
+	}
+}
+impl<A: Key> Filter for In<A> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(Some(Clause::new(format!("{} IN (SELECT value FROM json_each(?))", self.variable.key(data_key)),
+			vec![self.values.value()?])))
+	}
+}
+impl<A: Key> Filter for NotIn<A> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(Some(Clause::new(format!("{} NOT IN (SELECT value FROM json_each(?))", self.variable.key(data_key)),
+			vec![self.values.value()?])))
+	}
+}
+impl<A: Key> Filter for Between<A> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(Some(Clause::new(format!("{} BETWEEN ? AND ?", self.variable.key(data_key)),
+			vec![self.low.value()?, self.high.value()?])))
+	}
+}
+impl<A: Key> Filter for IsNull<A> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(Some(match self.0.json_type(data_key) {
+			Some(json_type) => format!("{} = 'null'", json_type),
+			None => format!("{} IS NULL", self.0.key(data_key)),
+		}.into()))
+	}
+}
+impl<A: Key> Filter for IsMissing<A> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		Ok(Some(match self.0.json_type(data_key) {
+			Some(json_type) => format!("{} IS NULL", json_type),
+			// Only fields in a JSON object can be missing
+			None => "0".to_string(),
+		}.into()))
+	}
+}
+impl<A: Key> Filter for IsType<A> {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		// Keys that aren't fields in a JSON object only have the SQL types
+		let json_type = self.variable.json_type(data_key)
+			.unwrap_or_else(|| format!("typeof({})", self.variable.key(data_key)));
+		Ok(Some(format!("{} = {}", json_type, quote_literal(self.json_type.as_str())).into()))
+	}
+}
+impl Filter for Search {
+	fn where_(&self, _: &str) -> Result<Option<Clause>> {
+		Ok(Some(Clause::new(format!("rowid IN (SELECT rowid FROM {0} WHERE {0} MATCH ?)", self.index),
+			vec![Value::Text(self.query.clone())])))
+	}
+}
+impl Filter for Contains {
+	fn where_(&self, data_key: &str) -> Result<Option<Clause>> {
+		let path = quote_literal(&self.path);
+		// json_each also goes through scalars, which would make a string contain itself
+		let is_array = format!("json_type({}, {}) IS 'array'", data_key, path);
+		let elements = format!("SELECT 1 FROM json_each({}, {}) WHERE value", data_key, path);
+		let params = self.values.iter().map(Param::value).collect::<Result<Vec<_>>>()?;
+		let sql = if self.all {
+			let exists = vec![format!(" AND EXISTS ({} = ?)", elements); params.len()];
+			format!("({}{})", is_array, exists.concat())
+		} else {
+			format!("({} AND EXISTS ({} IN ({})))", is_array, elements, vec!["?"; params.len()].join(", "))
+		};
+		Ok(Some(Clause::new(sql, params)))
+	}
+}
+
+/// Joins two optional clauses with a logical operator.
+///
+/// If only one of the clauses exists, it is returned as is.
+fn join(first: Option<Clause>, operator: &str, second: Option<Clause>) -> Option<Clause> {
+	match (first, second) {
+		(Some(mut first), Some(second)) => {
+			first.params.extend(second.params);
+			Some(Clause::new(format!("({} {} {})", first.sql, operator, second.sql), first.params))
+		},
+		(first, second) => first.or(second),
 	}
 }
 
@@ -247,6 +596,13 @@ impl Sort for () {
 impl<K: Key> Sort for SortOrder<K> {
 	fn order_by(&self, data_key: &str) -> Vec<String> { vec![self.key(data_key)]	}
 }
+impl Sort for Search {
+	fn order_by(&self, _: &str) -> Vec<String> {
+		// Sorts can't bind parameters so the query is quoted instead
+		vec![format!("(SELECT rank FROM {0} WHERE {0} MATCH {1} AND {0}.rowid = {2}.rowid)",
+			self.index, quote_literal(&self.query), self.table)]
+	}
+}
 impl<A: Sort, B: Sort> Sort for And<A, B> {
 	fn order_by(&self, data_key: &str) -> Vec<String> {
 		let mut first = self.first.order_by(data_key);
@@ -270,9 +626,11 @@ impl<K, V> Entry<K, V> {
 	}
 }
 impl<K: FromSql, V: DeserializeOwned> Entry<K, V> {
-	fn from_row(row: &Row) -> SqliteResult<Entry<K, V>> {
+	fn from_row(row: &Row) -> Result<Entry<K, V>> {
 		let id = row.get(0)?;
-		let data = row.get::<_, Json<V>>(1)?.unwrap();
+		let data = row.get::<_, Json<V>>(1)
+			.map_err(|error| Error::from(error).at(row.get(0).ok(), Some("$")))?
+			.unwrap();
 		Ok(Entry{ id, data })
 	}
 }
@@ -303,7 +661,7 @@ impl<T: DeserializeOwned> FromSql for Json<T> {
 	}
 }
 impl<T: Serialize> ToSql for Json<T> {
-	fn to_sql(&self) -> SqliteResult<ToSqlOutput> {
+	fn to_sql(&self) -> SqliteResult<ToSqlOutput<'_>> {
 		let Json(data) = &self;
 		Ok(ToSqlOutput::Owned(Value::Text(to_string(data).map_err(|err| SqliteError::ToSqlConversionFailure(Box::new(err)))?)))
 	}
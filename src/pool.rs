@@ -0,0 +1,133 @@
+use rusqlite::Connection as SqliteConnection;
+
+use std::{ops::Deref, path::Path, sync::{Condvar, Mutex, PoisonError}, time::Duration};
+
+use crate::{Connection, JournalMode, lock, Result};
+
+/// A pool of connections to a database that can be shared between threads.
+///
+/// The database uses SQLite's write-ahead log so that reading doesn't block writing.
+/// The pool has a single connection that can write to the database, as SQLite only allows one writer at a time,
+/// and a number of read-only connections.
+/// Use [`writer`] to change the database and [`reader`] to read from it.
+/// Both wait until a connection is free, and a connection is returned to the pool when it is dropped.
+///
+/// [`writer`]: #method.writer
+/// [`reader`]: #method.reader
+///
+/// # Example
+///
+/// ```
+/// # use nosqlite::{Pool, json};
+/// # use std::{sync::Arc, thread, time::Duration};
+/// # let path = std::env::temp_dir().join("nosqlite_pool.db");
+/// # let _ = std::fs::remove_file(&path);
+/// let pool = Arc::new(Pool::open(&path, 4, Duration::from_secs(5))?);
+/// let table = Arc::new(pool.writer().table("people")?);
+/// table.insert(json!({"name": "Hiruna"}), &pool.writer())?;
+/// let handles: Vec<_> = (0..8).map(|_| {
+/// 	let (pool, table) = (pool.clone(), table.clone());
+/// 	thread::spawn(move || table.iter().count(&pool.reader()))
+/// }).collect();
+/// for handle in handles {
+/// 	assert_eq!(handle.join().unwrap()?, 1);
+/// }
+/// # drop(pool);
+/// # std::fs::remove_file(&path).ok();
+/// # nosqlite::Result::Ok(())
+/// ```
+pub struct Pool {
+	writer: Connections,
+	readers: Connections,
+}
+impl Pool {
+	/// Opens a pool of connections to a database, creating it if it doesn't exist.
+	///
+	/// The pool has one writer and `size` read-only connections.
+	/// A `size` of 0 is treated as 1 so that there is always a connection for [`reader`] to return.
+	/// A connection waits up to `busy_timeout` for the database to be unlocked before returning an error.
+	///
+	/// [`reader`]: #method.reader
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::Pool;
+	/// # use std::time::Duration;
+	/// # let path = std::env::temp_dir().join("nosqlite_pool_open.db");
+	/// let pool = Pool::open(&path, 0, Duration::from_secs(5))?;
+	/// pool.writer().table("people")?;
+	/// assert_eq!(pool.reader().tables()?, ["people"]);
+	/// # drop(pool);
+	/// # std::fs::remove_file(&path).ok();
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn open<P: AsRef<Path>>(path: P, size: usize, busy_timeout: Duration) -> Result<Self> {
+		let path = path.as_ref();
+		let writer = Connection::builder()
+			.journal_mode(JournalMode::Wal)
+			.busy_timeout(busy_timeout)
+			.open(path)?;
+		let reader = Connection::builder().busy_timeout(busy_timeout).read_only(true);
+		let readers = (0..size.max(1)).map(|_| reader.open(path)).collect::<Result<_>>()?;
+		Ok(Self { writer: Connections::new(vec![writer]), readers: Connections::new(readers) })
+	}
+
+	/// Gets the connection that can write to the database, waiting until it is free.
+	pub fn writer(&self) -> PooledConnection<'_> { self.writer.get() }
+
+	/// Gets a read-only connection, waiting until one is free.
+	///
+	/// Trying to change the database using this connection returns an error.
+	pub fn reader(&self) -> PooledConnection<'_> { self.readers.get() }
+}
+
+/// The connections in a pool that aren't being used.
+struct Connections {
+	free: Mutex<Vec<Connection>>,
+	returned: Condvar,
+}
+impl Connections {
+	fn new(connections: Vec<Connection>) -> Self {
+		Self { free: Mutex::new(connections), returned: Condvar::new() }
+	}
+
+	/// Takes a connection out of the pool, waiting until one is returned if there are none.
+	fn get(&self) -> PooledConnection<'_> {
+		let mut free = lock(&self.free);
+		loop {
+			match free.pop() {
+				Some(connection) => return PooledConnection { connection: Some(connection), pool: self },
+				None => free = self.returned.wait(free).unwrap_or_else(PoisonError::into_inner),
+			}
+		}
+	}
+}
+
+/// A [`Connection`] that has been taken from a [`Pool`] and is returned to it when dropped.
+///
+/// This can be used wherever a [`Connection`] can be used.
+///
+/// [`Connection`]: struct.Connection.html
+/// [`Pool`]: struct.Pool.html
+pub struct PooledConnection<'a> {
+	connection: Option<Connection>,
+	pool: &'a Connections,
+}
+impl Deref for PooledConnection<'_> {
+	type Target = Connection;
+	fn deref(&self) -> &Connection {
+		self.connection.as_ref().expect("the connection is only taken when dropped")
+	}
+}
+impl AsRef<SqliteConnection> for PooledConnection<'_> {
+	fn as_ref(&self) -> &SqliteConnection { (**self).as_ref() }
+}
+impl Drop for PooledConnection<'_> {
+	fn drop(&mut self) {
+		if let Some(connection) = self.connection.take() {
+			lock(&self.pool.free).push(connection);
+			self.pool.returned.notify_one();
+		}
+	}
+}
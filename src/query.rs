@@ -0,0 +1,124 @@
+use crate::Clause;
+
+/// The clauses of a query, kept separate so that they are always rendered in an order SQLite accepts.
+///
+/// The clauses are rendered as `WHERE ... ORDER BY ... LIMIT ... OFFSET ...`
+/// no matter which of them are used.
+///
+/// Normal users of this crate should not need to use this at all.
+///
+/// # Example
+///
+/// ```
+/// # use nosqlite::{Query, util::Clause};
+/// let query = Query {
+/// 	where_: Some(Clause::new("x > ?", vec![3.into()])),
+/// 	order_by: vec!["x DESC".into()],
+/// 	limit: Some(10),
+/// 	offset: Some(5),
+/// };
+/// let clauses = query.clauses();
+/// assert_eq!(clauses.sql, "WHERE x > ? ORDER BY x DESC LIMIT 10 OFFSET 5");
+/// assert_eq!(clauses.params, [3.into()]);
+/// ```
+///
+/// Every combination of filtering, sorting, skipping and taking works.
+///
+/// ```
+/// # use nosqlite::{Connection, field, json, Key};
+/// # let connection = Connection::in_memory()?;
+/// # let table = connection.table("test")?;
+/// let numbers = [5, 3, 9, 1, 7, 2, 8];
+/// for number in numbers.iter() {
+/// 	table.insert(json!({"n": number}), &connection)?;
+/// }
+/// for combination in 0..16 {
+/// 	let (filter, sort, skip, take) =
+/// 		(combination & 1 != 0, combination & 2 != 0, combination & 4 != 0, combination & 8 != 0);
+/// 	// Work out what SQLite should return
+/// 	let mut expected: Vec<i64> = numbers.iter().copied()
+/// 		.filter(|n| !filter || *n > 2)
+/// 		.collect();
+/// 	if sort { expected.sort_unstable_by(|a, b| b.cmp(a)); }
+/// 	let expected: Vec<i64> = expected.into_iter()
+/// 		.skip(if skip { 1 } else { 0 })
+/// 		.take(if take { 3 } else { usize::MAX })
+/// 		.collect();
+///
+/// 	let mut iter = table.iter().filter(if filter { Some(field("n").gt(2)) } else { None });
+/// 	if skip { iter = iter.skip(1); }
+/// 	if take { iter = iter.take(3); }
+/// 	let actual: Vec<i64> = if sort {
+/// 		iter.sort(field("n").descending()).field("n", &connection)?
+/// 	} else {
+/// 		iter.field("n", &connection)?
+/// 	};
+/// 	assert_eq!(actual, expected, "filter: {}, sort: {}, skip: {}, take: {}", filter, sort, skip, take);
+/// }
+/// # nosqlite::Result::Ok(())
+/// ```
+#[derive(Debug, Default)]
+pub struct Query {
+	/// The condition that the entries must meet.
+	pub where_: Option<Clause>,
+	/// The SQL expressions that are used to sort the entries, in order of priority.
+	pub order_by: Vec<String>,
+	/// The maximum number of entries.
+	pub limit: Option<u32>,
+	/// The number of entries to skip over.
+	pub offset: Option<u32>,
+}
+impl Query {
+	/// Renders the clauses so that they can be put after `SELECT ... FROM table`.
+	pub fn clauses(&self) -> Clause {
+		let mut clauses = Clause::default();
+		if let Some(where_) = &self.where_ {
+			clauses.sql.push_str("WHERE ");
+			clauses.sql.push_str(&where_.sql);
+			clauses.params.extend(where_.params.iter().cloned());
+		}
+		if !self.order_by.is_empty() {
+			push_clause(&mut clauses.sql, &format!("ORDER BY {}", self.order_by.join(", ")));
+		}
+		if self.limit.is_some() || self.offset.is_some() {
+			push_clause(&mut clauses.sql, &format!("LIMIT {} OFFSET {}",
+				self.limit.map(i64::from).unwrap_or(-1), self.offset.unwrap_or(0)));
+		}
+		clauses
+	}
+
+	/// Renders the clauses so that they can be put after an `UPDATE` or `DELETE` statement.
+	///
+	/// SQLite doesn't allow sorting, skipping or taking in these statements,
+	/// so if any of those are used, the entries are chosen using their id in a sub-query instead.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// for number in [4, 8, 6, 2].iter() {
+	/// 	table.insert(json!(number), &connection)?;
+	/// }
+	/// // Delete the two largest numbers
+	/// table.iter().sort(field("").descending()).take(2).delete(&connection)?;
+	/// let numbers: Vec<u8> = table.iter().data(&connection)?;
+	/// assert_eq!(numbers, [4, 2]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn mutation_clauses(&self, table: &str, id: &str) -> Clause {
+		if self.order_by.is_empty() && self.limit.is_none() && self.offset.is_none() {
+			return self.clauses();
+		}
+		let Clause { sql, params } = self.clauses();
+		Clause::new(format!("WHERE {1} IN (SELECT {1} FROM {0} {2})", table, id, sql), params)
+	}
+}
+
+fn push_clause(sql: &mut String, clause: &str) {
+	if !sql.is_empty() {
+		sql.push(' ');
+	}
+	sql.push_str(clause);
+}
@@ -0,0 +1,95 @@
+use rusqlite::{Row, Rows as SqliteRows, Statement, types::{FromSql, Value}};
+use serde::de::DeserializeOwned;
+
+use std::marker::PhantomData;
+
+use crate::{Entry, Json, Result, second_column};
+
+/// A query that reads its entries from the database one at a time instead of all at once.
+///
+/// Create this using [`Iterator::stream`].
+/// Only one entry is kept in memory at a time so the size of the result does not matter.
+///
+/// [`Iterator::stream`]: struct.Iterator.html#method.stream
+#[must_use = "This struct does not do anything until its rows are iterated through"]
+pub struct Stream<'c, I> {
+	pub(crate) statement: Statement<'c>,
+	pub(crate) params: Vec<Value>,
+	pub(crate) id_type: PhantomData<fn() -> I>,
+}
+impl<'c, I: FromSql> Stream<'c, I> {
+	/// Iterates through only the JSON objects.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// for number in 0..100 {
+	/// 	table.insert(json!({"number": number}), &connection)?;
+	/// }
+	/// let mut stream = table.iter()
+	/// 	.filter(field("number").gte(90))
+	/// 	.sort(field("number").descending())
+	/// 	.stream(&connection)?;
+	/// let mut total = 0;
+	/// for object in stream.data::<serde_json::Value>()? {
+	/// 	total += object?["number"].as_i64().unwrap();
+	/// }
+	/// assert_eq!(total, (90..100).sum::<i64>());
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn data<T: DeserializeOwned>(&mut self) -> Result<Rows<'_, T>> {
+		Ok(Rows { rows: self.statement.query(&self.params)?, from_row: data_from_row })
+	}
+
+	/// Iterates through the ids and the JSON objects.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, Entry, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// table.insert(json!("a"), &connection)?;
+	/// table.insert(json!("b"), &connection)?;
+	/// let mut stream = table.iter().stream(&connection)?;
+	/// let mut entries = stream.entry::<String>()?;
+	/// let entry: Entry<i64, String> = entries.next().unwrap()?;
+	/// assert_eq!((entry.id, entry.data.as_str()), (1, "a"));
+	/// let entry = entries.next().unwrap()?;
+	/// assert_eq!((entry.id, entry.data.as_str()), (2, "b"));
+	/// assert!(entries.next().is_none());
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn entry<T: DeserializeOwned>(&mut self) -> Result<Rows<'_, Entry<I, T>>> {
+		Ok(Rows { rows: self.statement.query(&self.params)?, from_row: Entry::from_row })
+	}
+}
+
+/// The entries being read from a [`Stream`].
+///
+/// Each entry has its own result, so an entry that can't be deserialised
+/// doesn't stop the other entries from being read.
+///
+/// [`Stream`]: struct.Stream.html
+pub struct Rows<'s, T> {
+	rows: SqliteRows<'s>,
+	from_row: fn(&Row) -> Result<T>,
+}
+impl<T> std::iter::Iterator for Rows<'_, T> {
+	type Item = Result<T>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		match self.rows.next() {
+			Ok(Some(row)) => Some((self.from_row)(row)),
+			Ok(None) => None,
+			Err(error) => Some(Err(error.into())),
+		}
+	}
+}
+
+fn data_from_row<T: DeserializeOwned>(row: &Row) -> Result<T> {
+	second_column(row, "$").map(Json::unwrap)
+}
@@ -1,10 +1,10 @@
-use rusqlite::{Connection as SqliteConnection, NO_PARAMS, OptionalExtension, Result as SqliteResult,
-				types::{FromSql, ToSql}};
+use rusqlite::{Connection as SqliteConnection, NO_PARAMS, OptionalExtension, types::{FromSql, ToSql, Value, ValueRef}};
 use serde::{de::DeserializeOwned, Serialize};
 
-use std::{fmt::Display, marker::PhantomData};
+use std::{collections::HashSet, marker::PhantomData, sync::{Arc, mpsc::Receiver, Mutex}};
 
-use crate::{Entry, format_key, Iterator, Json, Key};
+use crate::{Change, ChangeEvent, changelog, Changes, companion, Connection, Entry, Error, Field, Filter, format_key, Generated,
+	generated_index, id_value, Identifier, index_fields, Iterator, Json, Key, lock, quote, quote_literal, Result, search_index, Transaction};
 
 /// A table in the database.
 ///
@@ -17,16 +17,75 @@ pub struct Table<I> {
 	///
 	/// The default implementation uses an integer but the value does not matter.
 	/// e.g. a String UUID can be used.
-	pub id: String,
+	pub id: Identifier,
 	pub(crate) id_type: PhantomData<fn() -> I>, // The type of the id.
 	/// The column that stores the JSON object.
 	///
 	/// The default implementation uses a Non Null Text but the value can be nullable or a Blob.
-	pub data: String,
+	pub data: Identifier,
 	/// The name of the table.
-	pub name: String,
+	pub name: Identifier,
+	/// Whether the id column is an alias of the rowid, so the id of an inserted entry is its rowid.
+	pub(crate) rowid_id: bool,
+	/// The JSON paths that have a generated column, and the quoted name of their column.
+	pub(crate) generated: Mutex<Generated>,
 }
 impl<A> Table<A> {
+	/// Creates a table with the default `id` and `data` columns but doesn't check if it exists.
+	pub(crate) fn new<T: Into<String>>(name: T) -> Result<Self> {
+		Ok(Self {
+			id: Identifier::new("id")?,
+			id_type: PhantomData,
+			data: Identifier::new("data")?,
+			name: Identifier::new(name)?,
+			rowid_id: false,
+			generated: Mutex::default(),
+		})
+	}
+
+	/// The JSON paths that have a generated column, and the quoted name of their column.
+	pub(crate) fn generated(&self) -> Generated { lock(&self.generated).clone() }
+
+	/// Finds the generated columns that hold a field of the JSON objects so that filters and sorts use them.
+	pub(crate) fn find_generated(&self, connection: &SqliteConnection) -> Result<()> {
+		let table: Option<String> = connection.query_row(
+			"SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?",
+			[self.name.as_str()],
+			|row| row.get(0),
+		).optional()?;
+		let table = match table {
+			Some(table) => table,
+			None => return Ok(()),
+		};
+		// Generated columns are hidden from pragma_table_info, and are either virtual (2) or stored (3)
+		let mut statement = connection.prepare("SELECT name FROM pragma_table_xinfo(?) WHERE hidden IN (2, 3)")?;
+		let columns = statement.query_map([self.name.as_str()], |row| row.get::<_, String>(0))?
+			.collect::<rusqlite::Result<Vec<_>>>()?;
+		for column in columns {
+			// Only columns that hold a field exactly as `generated_column` writes it can be used in its place
+			if let Some(path) = generated_expression(&table, &column).and_then(|expression| self.generated_path(&expression)) {
+				self.use_generated(path, quote(&column));
+			}
+		}
+		Ok(())
+	}
+
+	/// Gets the JSON path of a field that was rendered as `json_extract(data, 'path')`.
+	fn generated_path(&self, expression: &str) -> Option<String> {
+		let literal = expression.strip_prefix(&format!("json_extract({}, ", self.data))?.strip_suffix(')')?;
+		let path = literal.strip_prefix('\'')?.strip_suffix('\'')?.replace("''", "'");
+		Some(path).filter(|path| quote_literal(path) == literal)
+	}
+
+	/// Uses a generated column instead of the JSON field that it holds.
+	fn use_generated(&self, path: String, column: String) {
+		let mut generated = lock(&self.generated);
+		let mut columns = generated.as_ref().clone();
+		columns.retain(|(generated, _)| *generated != path);
+		columns.push((path, column));
+		*generated = Arc::new(columns);
+	}
+
 	/// Creates an index on the table with the given keys.
 	///
 	/// This is meant to speed up queries but whether it is actually used or not is determined
@@ -40,7 +99,7 @@ impl<A> Table<A> {
 	/// # let connection = Connection::in_memory()?;
 	/// # let table = connection.table("test")?;
 	/// table.index("my_index", &[field("name"), field("age")], connection)?;
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
 	///
 	/// If you want to index both a field and a column then you need to cast the reference
@@ -50,16 +109,132 @@ impl<A> Table<A> {
 	/// # let connection = Connection::in_memory()?;
 	/// # let table = connection.table("test")?;
 	/// table.index("my_index", &[&field("name") as &dyn Key, &column("id") as &dyn Key], connection)?;
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn index<S, I, T, C>(&self, name: S, keys: I, connection: C) -> Result<()>
+		where
+			S: Into<String>,
+			I: IntoIterator<Item=T>,
+			T: Key,
+			C: AsRef<SqliteConnection>,
+	{
+		self.create_index(name, keys, (), false, false, connection.as_ref())
+	}
+
+	/// Creates an index on the table with the given keys if an index with the same name doesn't exist.
+	///
+	/// Unlike [`index`], this can be run every time the program starts.
+	/// The keys of an existing index aren't checked, so to change them use [`drop_index`] first.
+	///
+	/// [`index`]: #method.index
+	/// [`drop_index`]: #method.drop_index
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// table.ensure_index("my_index", &[field("name")], &connection)?;
+	/// table.ensure_index("my_index", &[field("name")], &connection)?;
+	/// assert_eq!(table.indexes(&connection)?.len(), 1);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn ensure_index<S, I, T, C>(&self, name: S, keys: I, connection: C) -> Result<()>
+		where
+			S: Into<String>,
+			I: IntoIterator<Item=T>,
+			T: Key,
+			C: AsRef<SqliteConnection>,
+	{
+		self.create_index(name, keys, (), false, true, connection.as_ref())
+	}
+
+	/// Creates an index that doesn't allow two entries to have the same values for the given keys.
+	///
+	/// Inserting or updating an entry so that it has the same values as another entry
+	/// returns an [`Error::UniqueViolation`].
+	///
+	/// [`Error::UniqueViolation`]: enum.Error.html#variant.UniqueViolation
+	///
+	/// # Example
+	///
 	/// ```
-	pub fn index<S, I, T, C>(&self, name: S, keys: I, connection: C) -> SqliteResult<()>
+	/// # use nosqlite::{Connection, Error, field, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("users")?;
+	/// table.unique_index("users_email", &[field("email")], &connection)?;
+	/// table.insert(json!({"name": "Hiruna", "email": "hiruna@example.com"}), &connection)?;
+	/// match table.insert(json!({"name": "Bob", "email": "hiruna@example.com"}), &connection) {
+	/// 	Err(Error::UniqueViolation { index, fields }) => {
+	/// 		assert_eq!(index.as_deref(), Some("users_email"));
+	/// 		assert_eq!(fields, ["$.email"]);
+	/// 	},
+	/// 	_ => panic!("Expected the email to already be used"),
+	/// }
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn unique_index<S, I, T, C>(&self, name: S, keys: I, connection: C) -> Result<()>
 		where
-			S: Display,
+			S: Into<String>,
 			I: IntoIterator<Item=T>,
 			T: Key,
 			C: AsRef<SqliteConnection>,
 	{
-		let keys = keys.into_iter().map(|k| k.key(&self.data))
+		self.create_index(name, keys, (), true, false, connection.as_ref())
+	}
+
+	/// Creates an index that only includes the entries that meet the condition.
+	///
+	/// If `unique` is true then only the entries that meet the condition need to have unique values.
+	/// Any values in the filter are written into the index's SQL as SQLite doesn't allow parameters there.
+	/// The filter must always give the same result for an entry, so it can't use functions such as `random()`,
+	/// and it can't use subqueries, so filters such as [`Field::contains`] and full-text search
+	/// return an [`Error::InvalidIndexFilter`].
+	///
+	/// [`Field::contains`]: struct.Field.html#method.contains
+	/// [`Error::InvalidIndexFilter`]: enum.Error.html#variant.InvalidIndexFilter
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, Error, field, Filter, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("users")?;
+	/// // Deleted users don't need a unique email
+	/// let active = field("deleted").is_missing().or(field("deleted").eq(false));
+	/// table.partial_index("users_email", &[field("email")], active, true, &connection)?;
+	/// table.insert(json!({"email": "hiruna@example.com", "deleted": true}), &connection)?;
+	/// table.insert(json!({"email": "hiruna@example.com", "deleted": true}), &connection)?;
+	/// table.insert(json!({"email": "hiruna@example.com"}), &connection)?;
+	/// assert!(table.insert(json!({"email": "hiruna@example.com"}), &connection).is_err());
+	///
+	/// let result = table.partial_index("admins", &[field("email")], field("roles").contains("admin"), false, &connection);
+	/// assert!(matches!(result, Err(Error::InvalidIndexFilter(_))));
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn partial_index<S, I, T, F, C>(&self, name: S, keys: I, filter: F, unique: bool, connection: C) -> Result<()>
+		where
+			S: Into<String>,
+			I: IntoIterator<Item=T>,
+			T: Key,
+			F: Filter,
+			C: AsRef<SqliteConnection>,
+	{
+		self.create_index(name, keys, filter, unique, false, connection.as_ref())
+	}
+
+	/// Creates an index, which may be unique or only include the entries that meet the condition.
+	fn create_index<S, I, T, F>(&self, name: S, keys: I, filter: F, unique: bool, if_not_exists: bool,
+		connection: &SqliteConnection) -> Result<()>
+		where
+			S: Into<String>,
+			I: IntoIterator<Item=T>,
+			T: Key,
+			F: Filter,
+	{
+		let name = Identifier::new(name)?;
+		let keys = keys.into_iter().map(|k| k.key(self.data.quoted()))
 			.fold(String::new(), |mut s, k| {
 				if !s.is_empty() {
 					s.push(',');
@@ -67,10 +242,461 @@ impl<A> Table<A> {
 				s.push_str(k.as_str());
 				s
 			});
-		connection.as_ref().prepare(&format!("CREATE INDEX {} ON {} ({})", name, self.name, keys))?
-			.execute(NO_PARAMS).map(|_|())
+		let where_ = match filter.where_(self.data.quoted())? {
+			Some(clause) => format!(" WHERE {}", clause.inline()?),
+			None => String::new(),
+		};
+		let unique = if unique { "UNIQUE " } else { "" };
+		let if_not_exists = if if_not_exists { "IF NOT EXISTS " } else { "" };
+		connection.prepare(&format!("CREATE {}INDEX {}{} ON {} ({}){}", unique, if_not_exists, name, self.name, keys, where_))?
+			.execute(NO_PARAMS)?;
+		Ok(())
+	}
+
+	/// Receives the changes made to this table using the connection, once they have been committed.
+	///
+	/// See [`Connection::subscribe`] for more details.
+	///
+	/// [`Connection::subscribe`]: struct.Connection.html#method.subscribe
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{ChangeOp, Connection, json};
+	/// # use std::thread;
+	/// # let connection = Connection::in_memory()?;
+	/// let people = connection.table("people")?;
+	/// let pets = connection.table("pets")?;
+	/// let changes = people.watch(&connection);
+	/// let watcher = thread::spawn(move || changes.iter().map(|change| (change.op, change.id)).collect::<Vec<_>>());
+	/// people.insert(json!({"name": "Hiruna"}), &connection)?;
+	/// pets.insert(json!({"name": "Rex"}), &connection)?;
+	/// people.delete(1, &connection)?;
+	/// // The receiver stops once the connection is dropped
+	/// drop(connection);
+	/// assert_eq!(watcher.join().unwrap(), [(ChangeOp::Insert, 1), (ChangeOp::Delete, 1)]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn watch(&self, connection: &Connection) -> Receiver<ChangeEvent> {
+		Changes::subscribe(connection, Some(self.name.as_str().into()), HashSet::new())
+	}
+
+	/// Lists the indexes on the table.
+	///
+	/// This includes unique and partial indexes
+	/// but not the indexes that SQLite creates for the primary key.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("users")?;
+	/// table.index("users_name", &[field("name")], &connection)?;
+	/// table.partial_index("users_email", &[field("email")], field("deleted").is_missing(), true, &connection)?;
+	/// let indexes = table.indexes(&connection)?;
+	/// assert_eq!(indexes[0].name, "users_email");
+	/// assert_eq!(indexes[0].fields, ["$.email"]);
+	/// assert!(indexes[0].unique && indexes[0].partial);
+	/// assert_eq!(indexes[1].name, "users_name");
+	/// assert!(!indexes[1].unique && !indexes[1].partial);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn indexes<C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<Vec<Index>> {
+		let mut statement = connection.as_ref().prepare(r#"
+			SELECT list.name, list."unique", list.partial, master.sql
+			FROM pragma_index_list(?) AS list
+			JOIN sqlite_master AS master ON master.type = 'index' AND master.name = list.name
+			WHERE master.sql IS NOT NULL
+			ORDER BY list.name
+		"#)?;
+		let indexes = statement.query_map(&[self.name.as_str()], |row| Ok(Index {
+			name: row.get(0)?,
+			unique: row.get(1)?,
+			partial: row.get(2)?,
+			fields: index_fields(&row.get::<_, String>(3)?),
+		}))?.collect::<rusqlite::Result<_>>()?;
+		Ok(indexes)
+	}
+
+	/// Drops an index on the table, if it exists.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// table.index("my_index", &[field("name")], &connection)?;
+	/// table.drop_index("my_index", &connection)?;
+	/// assert!(table.indexes(&connection)?.is_empty());
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn drop_index<S: Into<String>, C: AsRef<SqliteConnection>>(&self, name: S, connection: C) -> Result<()> {
+		let name = Identifier::new(name)?;
+		connection.as_ref().execute(&format!("DROP INDEX IF EXISTS {}", name), NO_PARAMS)?;
+		Ok(())
+	}
+
+	/// Adds a generated column with the value of a field in the JSON objects and indexes it.
+	///
+	/// Any filters and sorts that use the same field are changed to use the column instead,
+	/// so that SQLite can always use the index.
+	/// If the column already exists, it is only used for filters and sorts,
+	/// so this can be called every time the table is opened.
+	/// Columns that were added before are also found when the table is opened using [`Connection::table`].
+	///
+	/// A `VIRTUAL` column is worked out whenever it is read while a `STORED` column is saved in the table.
+	/// SQLite can't add a `STORED` column to a table that exists,
+	/// so the table is rebuilt along with its indexes and triggers.
+	///
+	/// [`Connection::table`]: struct.Connection.html#method.table
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json, Key};
+	/// # let connection = Connection::in_memory()?;
+	/// let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna", "age": 19}), &connection)?;
+	/// table.insert(json!({"name": "Bob", "age": 13}), &connection)?;
+	/// table.generated_column("age", field("age"), "INTEGER", false, &connection)?;
+	/// table.generated_column("name", field("name"), "TEXT", true, &connection)?;
+	/// // The column's index is used instead of the JSON field
+	/// let plan: String = table.iter().filter(field("age").gt(18)).execute(
+	/// 	"EXPLAIN QUERY PLAN SELECT id",
+	/// 	|mut statement, params| Ok(statement.query_row(&params, |row| row.get(3))?),
+	/// 	&connection,
+	/// )?;
+	/// assert!(plan.contains("people_age"));
+	/// let names: Vec<String> = table.iter()
+	/// 	.filter(field("age").gt(18))
+	/// 	.sort(field("name").ascending())
+	/// 	.field("name", &connection)?;
+	/// assert_eq!(names, ["Hiruna"]);
+	/// // The columns follow any changes to the JSON objects
+	/// table.get(2).set("age", 20, &connection)?;
+	/// assert_eq!(table.iter().filter(field("age").gt(18)).count(&connection)?, 2);
+	/// // The columns are used when the table is opened again
+	/// let plan: String = connection.table("people")?.iter().filter(field("age").gt(18)).execute(
+	/// 	"EXPLAIN QUERY PLAN SELECT id",
+	/// 	|mut statement, params| Ok(statement.query_row(&params, |row| row.get(3))?),
+	/// 	&connection,
+	/// )?;
+	/// assert!(plan.contains("people_age"));
+	/// // The type can't be used to add anything else to the table
+	/// assert!(table.generated_column("evil", field("age"), "INTEGER, evil TEXT", false, &connection).is_err());
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	///
+	/// Rebuilding the table keeps the views, triggers and foreign keys that refer to it.
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, json};
+	/// # use rusqlite::NO_PARAMS;
+	/// # let connection = Connection::in_memory()?;
+	/// connection.as_ref().execute_batch("PRAGMA foreign_keys = ON")?;
+	/// let table = connection.table("people")?;
+	/// table.insert(json!({"name": "Hiruna"}), &connection)?;
+	/// connection.as_ref().execute_batch(r#"
+	/// 	CREATE VIEW names AS SELECT json_extract(data, '$.name') AS name FROM people;
+	/// 	CREATE TABLE pets (owner INTEGER REFERENCES people (id) ON DELETE CASCADE);
+	/// 	INSERT INTO pets VALUES (1);
+	/// 	CREATE TRIGGER adopted AFTER INSERT ON pets BEGIN
+	/// 		UPDATE people SET data = json_set(data, '$.pets', coalesce(json_extract(data, '$.pets'), 0) + 1) WHERE id = new.owner;
+	/// 	END;
+	/// "#)?;
+	/// table.generated_column("name", field("name"), "TEXT", true, &connection)?;
+	/// let pets: i64 = connection.as_ref().query_row("SELECT count(*) FROM pets", NO_PARAMS, |row| row.get(0))?;
+	/// assert_eq!(pets, 1);
+	/// let name: String = connection.as_ref().query_row("SELECT name FROM names", NO_PARAMS, |row| row.get(0))?;
+	/// assert_eq!(name, "Hiruna");
+	/// connection.as_ref().execute("INSERT INTO pets VALUES (1)", NO_PARAMS)?;
+	/// assert_eq!(table.get(1).field::<i64, _>("pets", &connection)?, Some(1));
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	///
+	/// Tables with constraints after their columns, or without a rowid, can be rebuilt too.
+	/// The column's type converts the values it holds,
+	/// so filters that use the column can match values that the JSON field alone wouldn't.
+	/// Leave the type empty to compare the values as they are.
+	///
+	/// ```
+	/// # use nosqlite::{Connection, field, Key, Table};
+	/// # let connection = Connection::in_memory()?;
+	/// connection.as_ref().execute_batch(r#"
+	/// 	CREATE TABLE codes (
+	/// 		code TEXT NOT NULL,
+	/// 		data TEXT NOT NULL, -- the JSON object
+	/// 		PRIMARY KEY (code)
+	/// 	) WITHOUT ROWID;
+	/// 	INSERT INTO codes VALUES ('a', '{"n": "19"}');
+	/// "#)?;
+	/// let table: Table<String> = Table::unchecked::<String, _>("code", "data", "codes")?;
+	/// assert_eq!(table.iter().filter(field("n").eq(19)).count(&connection)?, 0);
+	/// table.generated_column("n", field("n"), "INTEGER", true, &connection)?;
+	/// // The text "19" is stored as the number 19 in an INTEGER column
+	/// assert_eq!(table.iter().filter(field("n").eq(19)).count(&connection)?, 1);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn generated_column<S, C>(&self, name: S, field: Field, sql_type: &str, stored: bool, connection: C) -> Result<()>
+		where
+			S: Into<String>,
+			C: AsRef<SqliteConnection>,
+	{
+		let column = Identifier::new(name)?;
+		if !is_type_name(sql_type) {
+			return Err(Error::InvalidIdentifier {
+				name: sql_type.into(),
+				reason: "the type can only be a type name with an optional size, such as DECIMAL(10, 5)",
+			});
+		}
+		let index = Identifier::new(generated_index(self.name.as_str(), column.as_str()))?;
+		let expression = field.key(self.data.quoted());
+		let connection = connection.as_ref();
+		let exists: bool = connection.query_row(
+			"SELECT count(*) FROM pragma_table_xinfo(?) WHERE name = ?",
+			[self.name.as_str(), column.as_str()],
+			|row| row.get(0),
+		)?;
+		// Another table could have an index with the same name, which makes creating this one fail
+		let indexed: bool = connection.query_row(
+			"SELECT count(*) FROM sqlite_master WHERE type = 'index' AND name = ? AND tbl_name = ?",
+			[index.as_str(), self.name.as_str()],
+			|row| row.get(0),
+		)?;
+		let definition = format!("{} {} GENERATED ALWAYS AS ({}) {}",
+			column, sql_type, expression, if stored { "STORED" } else { "VIRTUAL" });
+		let create_index = if indexed { String::new() } else { format!("CREATE INDEX {} ON {} ({})", index, self.name, column) };
+		if !exists && stored {
+			self.rebuild(&definition, &create_index, connection)?;
+		} else {
+			Transaction::atomic(connection, |connection| {
+				if !exists {
+					connection.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {}", self.name, definition))?;
+				}
+				Ok(connection.execute_batch(&create_index)?)
+			})?;
+		}
+		self.use_generated(field.0, column.quoted().into());
+		Ok(())
+	}
+
+	/// Rebuilds the table with an extra column and then runs `then`, all inside of one transaction.
+	///
+	/// This follows [SQLite's steps](https://www.sqlite.org/lang_altertable.html#otheralter) for changing a table,
+	/// so its entries, indexes and triggers are kept along with the views and triggers on other tables that refer to it.
+	fn rebuild(&self, column: &str, then: &str, connection: &SqliteConnection) -> Result<()> {
+		let cannot_rebuild = |reason| Error::CannotRebuild { table: self.name.as_str().into(), reason };
+		// Dropping the table would delete the entries that refer to it, or fail, if foreign keys are enforced
+		let foreign_keys: bool = connection.query_row("PRAGMA foreign_keys", NO_PARAMS, |row| row.get(0))?;
+		if foreign_keys && !connection.is_autocommit() {
+			return Err(cannot_rebuild("foreign keys can't be turned off inside of a transaction"));
+		}
+		// The views and triggers that refer to the table would stop the new table from being renamed,
+		// unless the renaming leaves the rest of the schema alone
+		let legacy_alter_table: bool = connection.query_row("PRAGMA legacy_alter_table", NO_PARAMS, |row| row.get(0))?;
+		connection.execute_batch("PRAGMA foreign_keys = OFF; PRAGMA legacy_alter_table = ON")?;
+		let result = Transaction::atomic(connection, |connection| {
+			let table: String = connection.query_row(
+				"SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?",
+				[self.name.as_str()],
+				|row| row.get(0),
+			).optional()?.ok_or_else(|| Error::MissingTable(self.name.as_str().into()))?;
+			if table.to_ascii_uppercase().starts_with("CREATE VIRTUAL TABLE") {
+				return Err(cannot_rebuild("it is a virtual table"));
+			}
+			let (start, columns_end, end) = column_positions(&table)
+				.ok_or_else(|| cannot_rebuild("the SQL that created it couldn't be read"))?;
+			let rowid = !table[end..].to_ascii_uppercase().split(|c: char| !c.is_ascii_alphabetic()).any(|word| word == "WITHOUT");
+			let new = quote(&format!("{}_new", self.name.as_str()));
+			let table = format!("CREATE TABLE {} {}, {}{}", new, &table[start..columns_end], column, &table[columns_end..]);
+			// The indexes and triggers on the table are dropped along with it
+			let mut statement = connection.prepare(
+				"SELECT sql FROM sqlite_master WHERE tbl_name = ? AND type IN ('index', 'trigger') AND sql IS NOT NULL")?;
+			let dependents = statement.query_map([self.name.as_str()], |row| row.get(0))?
+				.collect::<rusqlite::Result<Vec<String>>>()?;
+			// Generated columns are hidden from pragma_table_info, and can't be copied anyway
+			let mut statement = connection.prepare("SELECT name FROM pragma_table_info(?)")?;
+			let mut columns = statement.query_map([self.name.as_str()], |row| row.get::<_, String>(0))?
+				.map(|name| name.map(|name| quote(&name)))
+				.collect::<rusqlite::Result<Vec<_>>>()?;
+			if rowid {
+				columns.insert(0, "rowid".into());
+			}
+			connection.execute_batch(&format!(r#"
+				{table};
+				INSERT INTO {new} ({columns}) SELECT {columns} FROM {name};
+				DROP TABLE {name};
+				ALTER TABLE {new} RENAME TO {name};
+				{dependents};
+				{then};
+			"#,
+				table = table,
+				new = new,
+				columns = columns.join(", "),
+				name = self.name,
+				dependents = dependents.join(";\n"),
+				then = then,
+			))?;
+			if foreign_keys && connection.prepare("PRAGMA foreign_key_check")?.exists(NO_PARAMS)? {
+				return Err(cannot_rebuild("an entry would break a foreign key"));
+			}
+			Ok(())
+		});
+		connection.execute_batch(&format!("PRAGMA foreign_keys = {}; PRAGMA legacy_alter_table = {}",
+			foreign_keys as u8, legacy_alter_table as u8))?;
+		result
+	}
+
+	/// Creates a full-text index on the table using the given fields of the JSON objects.
+	///
+	/// The index is an FTS5 virtual table called `{table}_search` that is kept up to date
+	/// by triggers whenever an entry is inserted, updated or deleted.
+	/// Any entries that are already in the table are added to the index.
+	/// A table can only have one full-text index,
+	/// so if it already exists this does nothing and the fields it uses aren't changed.
+	///
+	/// Use [`Iterator::search`] to search through the index.
+	///
+	/// [`Iterator::search`]: struct.Iterator.html#method.search
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("posts")?;
+	/// table.insert(json!({"title": "Hello", "author": {"name": "Hiruna"}}), &connection)?;
+	/// table.search_index(&["title", "author.name"], &connection)?;
+	/// table.insert(json!({"title": "Goodbye", "author": {"name": "Bob"}}), &connection)?;
+	/// assert_eq!(table.iter().search("hiruna").id(&connection)?, [1]);
+	/// // The index is updated along with the table
+	/// table.get(2).set("title", "Hello again", &connection)?;
+	/// assert_eq!(table.iter().search("hello").id(&connection)?, [1, 2]);
+	/// table.delete(1, &connection)?;
+	/// assert_eq!(table.iter().search("hello").id(&connection)?, [2]);
+	/// // This can be run every time the program starts
+	/// table.search_index(&["title", "author.name"], &connection)?;
+	/// assert_eq!(table.iter().search("hello").id(&connection)?, [2]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn search_index<F, S, C>(&self, fields: F, connection: C) -> Result<()>
+		where
+			F: IntoIterator<Item=S>,
+			S: AsRef<str>,
+			C: AsRef<SqliteConnection>,
+	{
+		let index = Identifier::new(search_index(self.name.as_str()))?;
+		let paths: Vec<String> = fields.into_iter().map(|field| format_key(field.as_ref())).collect();
+		let columns = paths.iter().map(|path| quote(path)).collect::<Vec<_>>().join(", ");
+		Transaction::atomic(connection.as_ref(), |connection| {
+			let exists: bool = connection.query_row(
+				"SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+				[index.as_str()],
+				|row| row.get(0),
+			)?;
+			// The entries are already in an index that exists
+			if !exists {
+				connection.execute_batch(&format!(r#"
+					CREATE VIRTUAL TABLE IF NOT EXISTS {index} USING fts5({columns});
+					INSERT INTO {index} (rowid, {columns}) SELECT rowid, {values} FROM {table};
+				"#,
+					index = index,
+					columns = columns,
+					values = companion::search_values(self.name.quoted(), &self.data, &paths),
+					table = self.name,
+				))?;
+			}
+			companion::record(connection, companion::Kind::Search, &index, self)?;
+			Ok(connection.execute_batch(&companion::search_triggers(&self.name, &self.data, &index, &paths))?)
+		})
+	}
+
+	/// Records every insert, update and delete on the table in a changelog, which is kept up to date using triggers.
+	///
+	/// Each change is given a sequence number that is larger than every one before it, even after compaction,
+	/// so a client only needs to remember the last sequence number it has seen to catch up using [`changes_since`].
+	/// This can be run every time the program starts.
+	///
+	/// [`changes_since`]: #method.changes_since
+	pub fn changelog<C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<()> {
+		let changelog = Identifier::new(changelog(self.name.as_str()))?;
+		Transaction::atomic(connection.as_ref(), |connection| {
+			connection.execute_batch(&format!(r#"
+				CREATE TABLE IF NOT EXISTS {changelog} (
+					seq INTEGER PRIMARY KEY AUTOINCREMENT,
+					op TEXT NOT NULL,
+					id NOT NULL
+				);
+				{triggers}
+			"#,
+				changelog = changelog,
+				triggers = companion::changelog_triggers(&self.name, &self.id, &changelog),
+			))?;
+			companion::record(connection, companion::Kind::Changelog, &changelog, self)
+		})
+	}
+
+	/// Removes the changes up to and including `seq` from the changelog
+	/// if a later change to the same entry was also recorded, and returns how many were removed.
+	///
+	/// The last change to each entry is always kept, so every client can still catch up
+	/// but it may not see every change that was made to an entry.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.changelog(&connection)?;
+	/// table.insert(json!({"name": "Hiruna"}), &connection)?;
+	/// table.insert(json!({"name": "Bob"}), &connection)?;
+	/// table.get(1).set("age", 19, &connection)?;
+	/// table.get(1).set("age", 20, &connection)?;
+	/// assert_eq!(table.compact_changes(3, &connection)?, 2);
+	/// let seqs: Vec<i64> = table.changes_since(0, &connection)?.into_iter().map(|change| change.seq).collect();
+	/// assert_eq!(seqs, [2, 4]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn compact_changes<C: AsRef<SqliteConnection>>(&self, seq: i64, connection: C) -> Result<usize> {
+		let changelog = Identifier::new(changelog(self.name.as_str()))?;
+		let removed = connection.as_ref().execute(&format!(
+			"DELETE FROM {0} WHERE seq <= ? AND EXISTS (SELECT 1 FROM {0} AS later WHERE later.id = {0}.id AND later.seq > {0}.seq)",
+			changelog,
+		), [seq])?;
+		Ok(removed)
+	}
+}
+impl<I> Clone for Table<I> {
+	fn clone(&self) -> Self {
+		Self {
+			id: self.id.clone(),
+			id_type: PhantomData,
+			data: self.data.clone(),
+			name: self.name.clone(),
+			rowid_id: self.rowid_id,
+			generated: Mutex::new(self.generated()),
+		}
 	}
 }
+
+/// An index on a table, as listed by [`Table::indexes`].
+///
+/// [`Table::indexes`]: struct.Table.html#method.indexes
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Index {
+	/// The name of the index.
+	pub name: String,
+	/// Whether two entries can't have the same values for the keys of the index.
+	pub unique: bool,
+	/// Whether the index only includes the entries that meet a condition.
+	pub partial: bool,
+	/// The keys of the index, with fields in a JSON object given as their JSON path.
+	pub fields: Vec<String>,
+}
+
 impl<I: FromSql> Table<I> {
 	/// Creates a table but doesn't check if the table exists.
 	///
@@ -78,14 +704,19 @@ impl<I: FromSql> Table<I> {
 	///
 	/// Generally using [`Connection::table`] is recommended instead of this.
 	///
+	/// Returns an error if any of the names are not a valid [`Identifier`].
+	///
 	/// [`Connection::table`]: struct.Connection.html#method.table
-	pub fn unchecked<K: FromSql, T: Into<String>>(id: T, data: T, name: T) -> Self {
-		Self {
-			id: id.into(),
-			data: data.into(),
-			name: name.into(),
-			id_type: PhantomData::default(),
-		}
+	/// [`Identifier`]: struct.Identifier.html
+	pub fn unchecked<K: FromSql, T: Into<String>>(id: T, data: T, name: T) -> Result<Self> {
+		Ok(Self {
+			id: Identifier::new(id)?,
+			data: Identifier::new(data)?,
+			name: Identifier::new(name)?,
+			id_type: PhantomData,
+			rowid_id: false,
+			generated: Mutex::default(),
+		})
 	}
 
 	/// Iterate through all the entries in the table.
@@ -103,17 +734,19 @@ impl<I: FromSql> Table<I> {
 	/// 	.fields::<(String, u8), _, _, _>(&["name", "age"], &connection)?;
 	/// assert_eq!(data.len(), 1);
 	/// assert!(data[0].1 > 18);
-	/// # Ok::<(), rusqlite::Error>(())
+	/// # Ok::<(), nosqlite::Error>(())
 	/// ```
-	pub fn iter(&self) -> Iterator<I, (), ()> {
+	pub fn iter(&self) -> Iterator<'_, I, (), ()> {
 		Iterator {
-			data_key: &self.data,
-			id_key: &self.id,
+			data_key: self.data.quoted(),
+			id_key: self.id.quoted(),
 			id_type: self.id_type,
 			limit: None,
 			offset: None,
 			order_by: (),
-			table_key: &self.name,
+			table_key: self.name.quoted(),
+			table_name: self.name.as_str(),
+			generated: self.generated(),
 			where_: (),
 		}
 	}
@@ -136,14 +769,116 @@ impl<I: FromSql> Table<I> {
 	/// table.insert(&json, &connection)?;
 	/// table.insert(&json, &connection)?;
 	/// // Now we'll check how many entries the table has
-	/// let length = table.iter().id(&connection)?.len();
+	/// let length = table.iter().count(&connection)?;
 	/// assert_eq!(length, 2);
-	/// # Ok::<(), rusqlite::Error>(())
+	/// # Ok::<(), nosqlite::Error>(())
 	/// ```
-	pub fn insert<T: Serialize, C: AsRef<SqliteConnection>>(&self, data: T, connection: C) -> SqliteResult<()> {
-		connection.as_ref().prepare(&format!("INSERT INTO {} ({}) VALUES (?)", self.name, self.data))?
-			.execute(&[&Json(data)])?;
-		Ok(())
+	///
+	/// The id that was given to the new entry is returned.
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json, Table};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// let id = table.insert(json!({"name": "Hiruna"}), &connection)?;
+	/// assert_eq!(id, 1);
+	/// let name: Option<String> = table.get(id).field("name", &connection)?;
+	/// assert_eq!(name.as_deref(), Some("Hiruna"));
+	/// # Ok::<(), nosqlite::Error>(())
+	/// ```
+	pub fn insert<T: Serialize, C: AsRef<SqliteConnection>>(&self, data: T, connection: C) -> Result<I> {
+		self.insert_one(data, connection.as_ref())
+	}
+
+	/// Inserts many JSON objects into the data column of the table and returns their ids.
+	///
+	/// This is much faster than calling [`insert`] for each JSON object
+	/// as all the objects are inserted inside of a single transaction.
+	/// If any of the objects fail to be inserted then none of them are.
+	/// If this is used inside of a [`Transaction`], it runs inside of a savepoint of that transaction instead.
+	///
+	/// [`insert`]: #method.insert
+	/// [`Transaction`]: struct.Transaction.html
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json, Table};
+	/// # use std::collections::BTreeMap;
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("numbers")?;
+	/// let ids = table.insert_many((0..1000).map(|n| json!({"n": n})), &connection)?;
+	/// assert_eq!(ids.len(), 1000);
+	/// assert_eq!(table.get(ids[10]).field::<i64, _>("n", &connection)?, Some(10));
+	///
+	/// // Inside of a transaction, none of the objects are inserted if one of them fails,
+	/// // such as a map whose keys can't be JSON keys
+	/// connection.transaction(|tx| {
+	/// 	let objects = vec![BTreeMap::new(), BTreeMap::from([((1, 2), 3)])];
+	/// 	assert!(table.insert_many(objects, tx).is_err());
+	/// 	nosqlite::Result::Ok(())
+	/// })?;
+	/// assert_eq!(table.iter().id(&connection)?.len(), 1000);
+	/// # Ok::<(), nosqlite::Error>(())
+	/// ```
+	pub fn insert_many<D, T, C>(&self, data: D, connection: C) -> Result<Vec<I>>
+	where
+		D: IntoIterator<Item=T>,
+		T: Serialize,
+		C: AsRef<SqliteConnection>,
+	{
+		Transaction::atomic(connection.as_ref(), |connection| data.into_iter()
+			.map(|data| self.insert_one(data, connection))
+			.collect())
+	}
+
+	/// Gets the changes recorded in the changelog after the change with the sequence number `seq`, in order.
+	///
+	/// Use a `seq` of 0 to get every change.
+	/// The changelog needs to be created using [`changelog`] first.
+	///
+	/// [`changelog`]: #method.changelog
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Change, ChangeOp, Connection, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.changelog(&connection)?;
+	/// table.insert(json!({"name": "Hiruna"}), &connection)?;
+	/// let last_seen = table.changes_since(0, &connection)?.last().unwrap().seq;
+	/// table.get(1).set("age", 19, &connection)?;
+	/// table.delete(1, &connection)?;
+	/// assert_eq!(table.changes_since(last_seen, &connection)?, [
+	/// 	Change { seq: 2, op: ChangeOp::Update, id: 1 },
+	/// 	Change { seq: 3, op: ChangeOp::Delete, id: 1 },
+	/// ]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn changes_since<C: AsRef<SqliteConnection>>(&self, seq: i64, connection: C) -> Result<Vec<Change<I>>> {
+		let changelog = Identifier::new(changelog(self.name.as_str()))?;
+		let mut statement = connection.as_ref()
+			.prepare(&format!("SELECT seq, op, id FROM {} WHERE seq > ? ORDER BY seq", changelog))?;
+		let changes = statement.query_map([seq], |row| Ok(Change { seq: row.get(0)?, op: row.get(1)?, id: row.get(2)? }))?
+			.collect::<rusqlite::Result<_>>()?;
+		Ok(changes)
+	}
+
+	/// Inserts a JSON object using cached statements so that inserting many objects is fast.
+	fn insert_one<T: Serialize>(&self, data: T, connection: &SqliteConnection) -> Result<I> {
+		connection.prepare_cached(&format!("INSERT INTO {} ({}) VALUES (?)", self.name, self.data))?
+			.execute(&[&Json(data)])
+			.map_err(|error| Error::from(error).with_index_fields(connection))?;
+		let rowid = connection.last_insert_rowid();
+		if self.rowid_id {
+			return I::column_result(ValueRef::Integer(rowid))
+				.map_err(|error| Error::Deserialization { id: Some(Value::Integer(rowid)), path: None, error: Box::new(error) });
+		}
+		// The id column isn't the rowid so it has to be looked up
+		let id = connection.prepare_cached(&format!("SELECT {} FROM {} WHERE rowid = ?", self.id, self.name))?
+			.query_row([rowid], |row| row.get(0))?;
+		Ok(id)
 	}
 }
 impl <I: FromSql + ToSql> Table<I> {
@@ -166,10 +901,10 @@ impl <I: FromSql + ToSql> Table<I> {
 	/// // Now we'll get the 2nd entry from the table.
 	/// let bobby: Person = table.get(2).data(&connection)?.unwrap();
 	/// assert_eq!(bobby.name, "Bobby");
-	/// # Ok::<(), rusqlite::Error>(())
+	/// # Ok::<(), nosqlite::Error>(())
 	/// ```
-	pub fn get(&self, id: I) -> Operation<I> {
-		Operation { id, data_key: &self.data, id_key: &self.id, table: &self.name }
+	pub fn get(&self, id: I) -> Operation<'_, I> {
+		Operation { id, data_key: self.data.quoted(), id_key: self.id.quoted(), table: self.name.quoted() }
 	}
 
 	/// Deletes an entry with the given primary key.
@@ -183,22 +918,23 @@ impl <I: FromSql + ToSql> Table<I> {
 	/// // We insert 2 entries
 	/// table.insert(json!(1), &connection)?;
 	/// table.insert(json!(2), &connection)?;
-	/// let length = table.iter().id(&connection)?.len();
+	/// let length = table.iter().count(&connection)?;
 	/// assert_eq!(length, 2);
 	/// // Remove the entry with id 1
 	/// table.delete(1, &connection)?;
 	/// // Table should only have entry now
-	/// let length = table.iter().id(&connection)?.len();
+	/// let length = table.iter().count(&connection)?;
 	/// assert_eq!(length, 1);
 	/// // And we shouldn't be able to access the entry with primary key 1 now
 	/// assert!(table.get(1).id(&connection)?.is_none());
-	/// # Ok::<(), rusqlite::Error>(())
+	/// # Ok::<(), nosqlite::Error>(())
 	/// ```
-	pub fn delete<C: AsRef<SqliteConnection>>(&self, id: I, connection: C) -> SqliteResult<()> {
+	pub fn delete<C: AsRef<SqliteConnection>>(&self, id: I, connection: C) -> Result<()> {
 		connection.as_ref().execute(
 			&format!("DELETE FROM {} WHERE {} = ?", self.name, self.id),
 			&[&id],
-		).map(|_|())
+		)?;
+		Ok(())
 	}
 }
 
@@ -211,11 +947,91 @@ impl <I: FromSql + ToSql> Table<I> {
 pub struct KeyTable<K>(pub Table<K>);
 impl<K: ToSql> KeyTable<K> {
 	/// Insert an entry to the table with a given key
-	pub fn insert<T: Serialize, C: AsRef<SqliteConnection>>(&self, key: K, data: T, connection: C) -> SqliteResult<()> {
+	///
+	/// The key is given back so that it can be used again.
+	pub fn insert<T: Serialize, C: AsRef<SqliteConnection>>(&self, key: K, data: T, connection: C) -> Result<K> {
+		self.insert_with(&key, data, "", connection)?;
+		Ok(key)
+	}
+
+	/// Inserts an entry with the given key or replaces the whole JSON object if the key already exists.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json, KeyTable};
+	/// # let connection = Connection::in_memory()?;
+	/// let table: KeyTable<String> = connection.key_table("test")?;
+	/// table.upsert("Point".into(), json!({ "x": 3, "y": 10 }), &connection)?;
+	/// table.upsert("Point".into(), json!({ "x": 5 }), &connection)?;
+	/// let point: serde_json::Value = table.as_ref().get("Point".into()).data(&connection)?.unwrap();
+	/// assert_eq!(point, json!({ "x": 5 }));
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn upsert<T: Serialize, C: AsRef<SqliteConnection>>(&self, key: K, data: T, connection: C) -> Result<K> {
 		let table = self.as_ref();
-		connection.as_ref().prepare(&format!("INSERT INTO {} ({}, {}) VALUES (?, ?)", table.name, table.id, table.data))?
-			.execute(&[&key as &dyn ToSql, &Json(data) as &dyn ToSql])?;
-		Ok(())
+		let conflict = format!("ON CONFLICT ({}) DO UPDATE SET {} = excluded.{}", table.id, table.data, table.data);
+		self.insert_with(&key, data, &conflict, connection)?;
+		Ok(key)
+	}
+
+	/// Inserts an entry with the given key or patches the JSON object if the key already exists.
+	///
+	/// The existing JSON object is merged with the new one in the same way as [`Operation::patch`].
+	///
+	/// [`Operation::patch`]: struct.Operation.html#method.patch
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json, KeyTable};
+	/// # let connection = Connection::in_memory()?;
+	/// let table: KeyTable<String> = connection.key_table("test")?;
+	/// table.upsert_patch("Point".into(), json!({ "x": 3, "y": 10 }), &connection)?;
+	/// table.upsert_patch("Point".into(), json!({ "x": 5 }), &connection)?;
+	/// let point: serde_json::Value = table.as_ref().get("Point".into()).data(&connection)?.unwrap();
+	/// assert_eq!(point, json!({ "x": 5, "y": 10 }));
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn upsert_patch<T: Serialize, C: AsRef<SqliteConnection>>(&self, key: K, data: T, connection: C) -> Result<K> {
+		let table = self.as_ref();
+		let conflict = format!("ON CONFLICT ({0}) DO UPDATE SET {1} = json_patch({1}, excluded.{1})", table.id, table.data);
+		self.insert_with(&key, data, &conflict, connection)?;
+		Ok(key)
+	}
+
+	/// Inserts an entry only if there isn't already an entry with the given key.
+	///
+	/// Returns whether the entry was inserted.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json, KeyTable};
+	/// # let connection = Connection::in_memory()?;
+	/// let table: KeyTable<String> = connection.key_table("test")?;
+	/// assert!(table.insert_if_absent("Point".into(), json!({ "x": 3 }), &connection)?);
+	/// assert!(!table.insert_if_absent("Point".into(), json!({ "x": 5 }), &connection)?);
+	/// assert_eq!(table.as_ref().get("Point".into()).field("x", &connection)?, Some(3));
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn insert_if_absent<T: Serialize, C: AsRef<SqliteConnection>>(&self, key: K, data: T, connection: C) -> Result<bool> {
+		let conflict = format!("ON CONFLICT ({}) DO NOTHING", self.as_ref().id);
+		Ok(self.insert_with(&key, data, &conflict, connection)? > 0)
+	}
+
+	/// Inserts an entry using the given conflict clause and returns the number of rows changed.
+	fn insert_with<T, C>(&self, key: &K, data: T, conflict: &str, connection: C) -> Result<usize>
+	where
+		T: Serialize,
+		C: AsRef<SqliteConnection>,
+	{
+		let (table, connection) = (self.as_ref(), connection.as_ref());
+		let changed = connection
+			.prepare(&format!("INSERT INTO {} ({}, {}) VALUES (?, ?) {}", table.name, table.id, table.data, conflict))?
+			.execute(&[key as &dyn ToSql, &Json(data) as &dyn ToSql])
+			.map_err(|error| Error::from(error).with_index_fields(connection))?;
+		Ok(changed)
 	}
 }
 impl<K> AsRef<Table<K>> for KeyTable<K> {
@@ -225,10 +1041,10 @@ impl<K> AsRef<Table<K>> for KeyTable<K> {
 /// Represents an operation to get a JSON object using its id key.
 #[must_use = "This struct must be used for the database to be queried."]
 pub struct Operation<'a, I: FromSql + ToSql> {
-	data_key: &'a str,
-	id: I,
-	id_key: &'a str,
-	table: &'a str,
+	pub(crate) data_key: &'a str,
+	pub(crate) id: I,
+	pub(crate) id_key: &'a str,
+	pub(crate) table: &'a str,
 }
 impl<'a, I: FromSql + ToSql> Operation<'a, I> {
 	/// Gets only the JSON object, deserialising it into the struct provided.
@@ -249,14 +1065,14 @@ impl<'a, I: FromSql + ToSql> Operation<'a, I> {
 	/// table.insert(Person{ name: "Bobby".into() }, &connection)?;
 	/// let bobby: Person = table.get(2).data(&connection)?.unwrap();
 	/// assert_eq!(bobby.name, "Bobby");
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn data<T: DeserializeOwned, C: AsRef<SqliteConnection>>(&self, connection: C) -> SqliteResult<Option<T>> {
+	pub fn data<T: DeserializeOwned, C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<Option<T>> {
 		connection.as_ref().query_row(
 			&format!("SELECT {} FROM {} WHERE {} = ?", self.data_key, self.table, self.id_key),
 			&[&self.id],
 			|row| row.get(0)
-		).map(Json::unwrap).optional()
+		).map(Json::unwrap).optional().map_err(|error| self.error(error, "$"))
 	}
 	/// Gets both the id and the JSON object.
 	///
@@ -277,14 +1093,13 @@ impl<'a, I: FromSql + ToSql> Operation<'a, I> {
 	/// let bobby: Entry<i64, Person> = table.get(2).entry(&connection)?.unwrap();
 	/// assert_eq!(bobby.data.name, "Bobby");
 	/// assert_eq!(bobby.id, 2);
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn entry<T: DeserializeOwned, C: AsRef<SqliteConnection>>(&self, connection: C) -> SqliteResult<Option<Entry<I, T>>> {
-		connection.as_ref().query_row(
-			&format!("SELECT {}, {} FROM {} WHERE {} = ?", self.id_key, self.data_key, self.table, self.id_key),
-			&[&self.id],
-			Entry::from_row
-		).optional()
+	pub fn entry<T: DeserializeOwned, C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<Option<Entry<I, T>>> {
+		let mut statement = connection.as_ref()
+			.prepare(&format!("SELECT {}, {} FROM {} WHERE {} = ?", self.id_key, self.data_key, self.table, self.id_key))?;
+		let mut rows = statement.query_and_then(&[&self.id], Entry::from_row)?;
+		rows.next().transpose()
 	}
 	/// Gets only the id of the entry.
 	///
@@ -304,14 +1119,14 @@ impl<'a, I: FromSql + ToSql> Operation<'a, I> {
 	/// table.insert(Person{ name: "Bobby".into() }, &connection)?;
 	/// let bobby_id: i64 = table.get(2).id(&connection)?.unwrap();
 	/// assert_eq!(bobby_id, 2);
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn id<C: AsRef<SqliteConnection>>(&self, connection: C) -> SqliteResult<Option<I>> {
+	pub fn id<C: AsRef<SqliteConnection>>(&self, connection: C) -> Result<Option<I>> {
 		connection.as_ref().query_row(
 			&format!("SELECT {} FROM {} WHERE {} = ?", self.id_key, self.table, self.id_key),
 			&[&self.id],
 			|row| row.get(0)
-		).optional()
+		).optional().map_err(Error::from)
 	}
 	/// Extracts a possibly nested field in the JSON object.
 	///
@@ -331,48 +1146,292 @@ impl<'a, I: FromSql + ToSql> Operation<'a, I> {
 	/// table.insert(Person{ name: "Bobby".into() }, &connection)?;
 	/// let bobby: String = table.get(2).field("name", &connection)?.unwrap();
 	/// assert_eq!(bobby, "Bobby");
-	/// # rusqlite::Result::Ok(())
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn field<T: FromSql, C: AsRef<SqliteConnection>>(&self, key: &str, connection: C) -> SqliteResult<Option<T>> {
+	pub fn field<T: FromSql, C: AsRef<SqliteConnection>>(&self, key: &str, connection: C) -> Result<Option<T>> {
 		let key = format_key(key);
 		connection.as_ref().query_row(
-			&format!("SELECT json_extract({}, \"{}\") FROM {} WHERE {} = ?", self.data_key, key, self.table, self.id_key),
-			&[&self.id],
+			&format!("SELECT json_extract({}, ?) FROM {} WHERE {} = ?", self.data_key, self.table, self.id_key),
+			&[&key as &dyn ToSql, &self.id],
 			|row| row.get(0)
-		).optional()
+		).optional().map_err(|error| self.error(error, &key))
 	}
 
 	/// Removes a *field* from a JSON object.
 	///
+	/// Returns `false` if there is no entry with this id.
+	///
 	/// # Example
 	///
 	/// ```
-	/// # use nosqlite::{Connection, Entry, Table, json};
+	/// # use nosqlite::{Connection, Entry, field, Key, Table, json};
 	/// # use serde::{Deserialize, Serialize};
 	/// # let connection = Connection::in_memory()?;
 	/// # let table = connection.table("people")?;
 	/// table.insert(json!({ "name": "Hiruna", "age": 19 }), &connection)?;
 	/// table.insert(json!({ "name": "Bob", "age": 13 }), &connection)?;
 	///
-	/// table.get(2).remove("age", &connection);
+	/// assert!(table.get(2).remove("age", &connection)?);
 	/// // Only 1 entry is returned because the second JSON object doesn't have an `age` field
 	/// // so only entry 1 has an age field
-	/// let people: Vec<(String, u8)> = table.iter().fields(&["name", "age"], &connection)?;
+	/// let people: Vec<(String, u8)> = table.iter()
+	/// 	.filter(field("age").exists())
+	/// 	.fields(&["name", "age"], &connection)?;
 	/// assert_eq!(people.len(), 1);
 	/// assert_eq!(people[0].0, "Hiruna");
 	/// // This *does not* delete the entry
-	/// assert_eq!(table.iter().id(&connection)?.len(), 2);
-	/// # rusqlite::Result::Ok(())
+	/// assert_eq!(table.iter().count(&connection)?, 2);
+	/// # nosqlite::Result::Ok(())
 	/// ```
-	pub fn remove<C>(&self, field: &str, connection: C) -> SqliteResult<()>
+	pub fn remove<C>(&self, field: &str, connection: C) -> Result<bool>
 		where C: AsRef<SqliteConnection>
 	{
 		let path = format_key(field);
-		let set_value = format!("{} = json_remove({}, '{}')", self.data_key, self.data_key, path);
-		connection.as_ref().execute(
-			&format!("UPDATE {} SET {} WHERE {} = ?", self.table, set_value, self.id_key),
-			&[&self.id]
-		).map(|_|())
+		let set_value = format!("{} = json_remove({}, ?)", self.data_key, self.data_key);
+		self.update(&set_value, &[&path], connection)
 	}
+
+	/// Inserts a field into the JSON object.
+	///
+	/// If the field already exists, nothing will happen.
+	/// If you wish for it to be overwritten, use [`set`] instead.
+	///
+	/// Returns `false` if there is no entry with this id.
+	///
+	/// [`set`]: #method.set
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({ "name": "Hiruna", "age": 19 }), &connection)?;
+	/// table.insert(json!({ "name": "Bob" }), &connection)?;
+	/// assert!(table.get(1).insert("age", 13, &connection)?);
+	/// assert!(table.get(2).insert("age", 13, &connection)?);
+	/// // Only Bob didn't have an age
+	/// assert_eq!(table.get(1).field::<u8, _>("age", &connection)?, Some(19));
+	/// assert_eq!(table.get(2).field::<u8, _>("age", &connection)?, Some(13));
+	/// // There is no entry with the id 3
+	/// assert!(!table.get(3).insert("age", 13, &connection)?);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn insert<T, C>(&self, field: &str, value: T, connection: C) -> Result<bool>
+	where
+		T: ToSql,
+		C: AsRef<SqliteConnection>,
+	{
+		let path = format_key(field);
+		let set_value = format!("{} = json_insert({}, ?, ?)", self.data_key, self.data_key);
+		self.update(&set_value, &[&path, &value], connection)
+	}
+
+	/// Uses a JSON object to update or create fields in the JSON object.
+	///
+	/// Any fields that do not exist will be created.
+	/// Arrays are replaced instead of being merged.
+	///
+	/// Returns `false` if there is no entry with this id.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({ "name": "Hiruna", "grades": [6, 8, 7] }), &connection)?;
+	/// assert!(table.get(1).patch(json!({ "age": 19, "grades": [9] }), &connection)?);
+	/// let person: serde_json::Value = table.get(1).data(&connection)?.unwrap();
+	/// assert_eq!(person, json!({ "name": "Hiruna", "age": 19, "grades": [9] }));
+	/// assert!(!table.get(2).patch(json!({ "age": 19 }), &connection)?);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn patch<T, C>(&self, value: T, connection: C) -> Result<bool>
+	where
+		T: Serialize,
+		C: AsRef<SqliteConnection>,
+	{
+		let set_value = format!("{} = json_patch({}, ?)", self.data_key, self.data_key);
+		self.update(&set_value, &[&Json(value)], connection)
+	}
+
+	/// Replaces a field in the JSON object.
+	///
+	/// If the field does not exist, nothing will happen.
+	/// If you wish for it to be created, use [`set`] instead.
+	///
+	/// Returns `false` if there is no entry with this id.
+	///
+	/// [`set`]: #method.set
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({ "name": "Hiruna", "age": 19 }), &connection)?;
+	/// table.insert(json!({ "name": "Bob" }), &connection)?;
+	/// assert!(table.get(1).replace("age", 20, &connection)?);
+	/// assert!(table.get(2).replace("age", 20, &connection)?);
+	/// // Only Hiruna had an age to replace
+	/// assert_eq!(table.get(1).field::<u8, _>("age", &connection)?, Some(20));
+	/// let bob: serde_json::Value = table.get(2).data(&connection)?.unwrap();
+	/// assert_eq!(bob, json!({ "name": "Bob" }));
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn replace<T, C>(&self, field: &str, value: T, connection: C) -> Result<bool>
+	where
+		T: ToSql,
+		C: AsRef<SqliteConnection>,
+	{
+		let path = format_key(field);
+		let set_value = format!("{} = json_replace({}, ?, ?)", self.data_key, self.data_key);
+		self.update(&set_value, &[&path, &value], connection)
+	}
+
+	/// Sets a field in the JSON object.
+	///
+	/// If the field does not exist, it will be created.
+	/// If the field does already exist, it will be overwritten.
+	/// If you wish for the value to not be overwritten, use [`insert`] instead.
+	///
+	/// Returns `false` if there is no entry with this id.
+	///
+	/// [`insert`]: #method.insert
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, json};
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("people")?;
+	/// table.insert(json!({ "name": "Hiruna" }), &connection)?;
+	/// assert!(table.get(1).set("address.city", "Auckland", &connection)?);
+	/// let city: Option<String> = table.get(1).field("address.city", &connection)?;
+	/// assert_eq!(city.as_deref(), Some("Auckland"));
+	/// assert!(!table.get(2).set("age", 13, &connection)?);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn set<T, C>(&self, field: &str, value: T, connection: C) -> Result<bool>
+	where
+		T: ToSql,
+		C: AsRef<SqliteConnection>,
+	{
+		let path = format_key(field);
+		let set_value = format!("{} = json_set({}, ?, ?)", self.data_key, self.data_key);
+		self.update(&set_value, &[&path, &value], connection)
+	}
+
+	/// Updates the entry with the id and returns whether it exists.
+	fn update<C: AsRef<SqliteConnection>>(&self, set_value: &str, values: &[&dyn ToSql], connection: C) -> Result<bool> {
+		let params = values.iter().copied().chain(std::iter::once(&self.id as &dyn ToSql));
+		let connection = connection.as_ref();
+		let changed = connection
+			.execute(&format!("UPDATE {} SET {} WHERE {} = ?", self.table, set_value, self.id_key), params)
+			.map_err(|error| Error::from(error).with_index_fields(connection))?;
+		Ok(changed > 0)
+	}
+
+	/// Attaches the id of the entry and the JSON path to any deserialisation errors.
+	fn error(&self, error: rusqlite::Error, path: &str) -> Error {
+		Error::from(error).at(id_value(&self.id), Some(path))
+	}
+}
+
+/// Gets the expression of a generated column from the SQL that created its table.
+fn generated_expression(table: &str, column: &str) -> Option<String> {
+	let start = table.find(&format!("{} ", quote(column)))?;
+	let start = start + table[start..].to_ascii_uppercase().find(" AS")?;
+	let start = start + table[start..].find('(')?;
+	let (mut depth, mut quote) = (0, None);
+	for (i, c) in table[start..].char_indices() {
+		match (quote, c) {
+			(Some(q), c) if c == q => quote = None,
+			(Some(_), _) => {},
+			(None, '\'') | (None, '"') => quote = Some(c),
+			(None, '(') => depth += 1,
+			(None, ')') => {
+				depth -= 1;
+				if depth == 0 {
+					return Some(table[start + 1..start + i].into());
+				}
+			},
+			_ => {},
+		}
+	}
+	None
+}
+
+/// Checks that a column type is a type name with an optional size, such as `TEXT` or `DECIMAL(10, 5)`.
+///
+/// An empty type is allowed, which leaves the values of the column as they are.
+fn is_type_name(sql_type: &str) -> bool {
+	let (name, size) = match sql_type.find('(') {
+		Some(start) => match sql_type[start + 1..].strip_suffix(')') {
+			Some(size) => (&sql_type[..start], Some(size)),
+			None => return false,
+		},
+		None => (sql_type, None),
+	};
+	// These start the constraints of a column rather than being part of its type
+	const CONSTRAINTS: &[&str] = &["AS", "CHECK", "COLLATE", "CONSTRAINT", "DEFAULT", "GENERATED", "NOT", "NULL", "PRIMARY",
+		"REFERENCES", "UNIQUE"];
+	let word = |word: &str| word.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+		&& word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+		&& !CONSTRAINTS.contains(&word.to_ascii_uppercase().as_str());
+	let number = |number: &str| !number.trim().is_empty() && number.trim().chars().all(|c| c.is_ascii_digit());
+	name.split_whitespace().all(word) && match size {
+		Some(size) => !name.trim().is_empty() && size.split(',').count() <= 2 && size.split(',').all(number),
+		None => true,
+	}
+}
+
+/// Finds where a column can be added to the SQL that created a table.
+///
+/// Returns where the bracket around the columns starts, where the last column ends
+/// (before any constraints on the whole table) and where the bracket ends.
+fn column_positions(table: &str) -> Option<(usize, usize, usize)> {
+	let (mut depth, mut start, mut columns_end) = (0, None, None);
+	let mut i = 0;
+	while i < table.len() {
+		match table.as_bytes()[i] {
+			// An escaped quote is two quotes, which is skipped over as two strings next to each other
+			b'\'' => i += 1 + table[i + 1..].find('\'')?,
+			b'"' => i += 1 + table[i + 1..].find('"')?,
+			b'`' => i += 1 + table[i + 1..].find('`')?,
+			b'[' => i += 1 + table[i + 1..].find(']')?,
+			b'-' if table[i..].starts_with("--") => i += table[i..].find('\n').unwrap_or(table.len() - i),
+			b'/' if table[i..].starts_with("/*") => i += 3 + table[i + 2..].find("*/")?,
+			b'(' => {
+				depth += 1;
+				if depth == 1 { start = Some(i); }
+			},
+			b')' => {
+				depth -= 1;
+				if depth == 0 { return Some((start?, columns_end.unwrap_or(i), i)); }
+			},
+			b',' if depth == 1 && columns_end.is_none() && starts_table_constraint(&table[i + 1..]) => columns_end = Some(i),
+			_ => {},
+		}
+		i += 1;
+	}
+	None
 }
 
+/// Checks whether the SQL starts with a constraint on a whole table, such as `PRIMARY KEY (a, b)`.
+fn starts_table_constraint(mut sql: &str) -> bool {
+	loop {
+		sql = sql.trim_start();
+		if sql.starts_with("--") {
+			sql = sql.find('\n').map_or("", |end| &sql[end..]);
+		} else if sql.starts_with("/*") {
+			sql = sql.find("*/").map_or("", |end| &sql[end + 2..]);
+		} else {
+			break;
+		}
+	}
+	let word = sql.split(|c: char| !c.is_ascii_alphabetic()).next().unwrap_or("").to_ascii_uppercase();
+	["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"].contains(&word.as_str())
+}
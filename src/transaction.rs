@@ -0,0 +1,119 @@
+use rusqlite::Connection as SqliteConnection;
+
+use crate::{Error, quote};
+
+/// A transaction that groups multiple operations together so that they either all succeed or all fail.
+///
+/// Create this using [`Connection::transaction`].
+/// This can be used wherever a [`Connection`] can be used.
+///
+/// [`Connection`]: struct.Connection.html
+/// [`Connection::transaction`]: struct.Connection.html#method.transaction
+pub struct Transaction<'a> {
+	connection: &'a SqliteConnection,
+	depth: u32,
+}
+impl<'a> Transaction<'a> {
+	/// Runs the closure inside of a transaction.
+	///
+	/// The transaction is committed if the closure returns `Ok`
+	/// and rolled back if it returns `Err` or panics.
+	pub(crate) fn run<T, E, F>(connection: &'a SqliteConnection, f: F) -> Result<T, E>
+	where
+		E: From<Error>,
+		F: FnOnce(&Transaction) -> Result<T, E>,
+	{
+		connection.execute_batch("BEGIN").map_err(Error::from)?;
+		let guard = Guard { connection, rollback: "ROLLBACK".into(), finished: false };
+		let value = f(&Transaction { connection, depth: 0 })?;
+		guard.finish("COMMIT")?;
+		Ok(value)
+	}
+
+	/// Runs the closure inside of a transaction
+	/// unless the connection is already inside of one, in which case it runs inside of a savepoint of that transaction.
+	pub(crate) fn atomic<T, F>(connection: &'a SqliteConnection, f: F) -> Result<T, Error>
+	where
+		F: FnOnce(&SqliteConnection) -> Result<T, Error>,
+	{
+		if connection.is_autocommit() {
+			Self::run(connection, |tx| f(tx.connection))
+		} else {
+			// SQLite uses the innermost savepoint with a name so reusing one that is already open is fine
+			Transaction { connection, depth: 0 }.savepoint(|tx| f(tx.connection))
+		}
+	}
+
+	/// Runs the closure inside of a savepoint, which is a transaction inside of this transaction.
+	///
+	/// The savepoint is released if the closure returns `Ok`
+	/// and rolled back if it returns `Err` or panics.
+	/// Rolling back a savepoint only undoes the changes made inside of it,
+	/// the outer transaction can still be committed.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # use nosqlite::{Connection, Error, json};
+	/// # use std::collections::HashMap;
+	/// # let connection = Connection::in_memory()?;
+	/// # let table = connection.table("test")?;
+	/// connection.transaction(|tx| {
+	/// 	table.insert(json!(1), tx)?;
+	/// 	// This savepoint fails so only its insert is undone
+	/// 	let result = tx.savepoint(|sp| {
+	/// 		table.insert(json!(2), sp)?;
+	/// 		table.insert(HashMap::from([((1, 2), 3)]), sp)
+	/// 	});
+	/// 	assert!(matches!(result, Err(Error::Serialization(_))));
+	/// 	tx.savepoint(|sp| table.insert(json!(3), sp))
+	/// })?;
+	/// let numbers: Vec<u8> = table.iter().data(&connection)?;
+	/// assert_eq!(numbers, [1, 3]);
+	/// # nosqlite::Result::Ok(())
+	/// ```
+	pub fn savepoint<T, E, F>(&self, f: F) -> Result<T, E>
+	where
+		E: From<Error>,
+		F: FnOnce(&Transaction) -> Result<T, E>,
+	{
+		let depth = self.depth + 1;
+		let name = quote(&format!("nosqlite_savepoint_{}", depth));
+		self.connection.execute_batch(&format!("SAVEPOINT {}", name)).map_err(Error::from)?;
+		let guard = Guard {
+			connection: self.connection,
+			rollback: format!("ROLLBACK TO {0}; RELEASE {0}", name),
+			finished: false,
+		};
+		let value = f(&Transaction { connection: self.connection, depth })?;
+		guard.finish(&format!("RELEASE {}", name))?;
+		Ok(value)
+	}
+}
+impl AsRef<SqliteConnection> for Transaction<'_> {
+	fn as_ref(&self) -> &SqliteConnection { self.connection }
+}
+
+/// Rolls back the transaction when dropped unless it has been finished.
+struct Guard<'a> {
+	connection: &'a SqliteConnection,
+	rollback: String,
+	finished: bool,
+}
+impl Guard<'_> {
+	/// Finishes the transaction using the given statement.
+	///
+	/// If this fails, the transaction is rolled back.
+	fn finish(mut self, statement: &str) -> Result<(), Error> {
+		self.connection.execute_batch(statement)?;
+		self.finished = true;
+		Ok(())
+	}
+}
+impl Drop for Guard<'_> {
+	fn drop(&mut self) {
+		if !self.finished {
+			let _ = self.connection.execute_batch(&self.rollback);
+		}
+	}
+}
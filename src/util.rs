@@ -1,6 +1,106 @@
 //! A module for utility structs that don't do much on their own
 
-use rusqlite::types::{FromSql, ToSql};
+use rusqlite::types::{FromSql, ToSql, Value};
+use serde::Serialize;
+
+use std::{cell::RefCell, sync::{Arc, Mutex, MutexGuard, PoisonError}};
+
+use crate::Error;
+
+/// A piece of SQL along with the values bound to the `?` parameters inside it.
+///
+/// The parameters are stored in the same order as the `?` placeholders appear in the SQL.
+#[derive(Clone, Debug, Default)]
+pub struct Clause {
+	/// The SQL expression.
+	pub sql: String,
+	/// The values bound to the parameters in the SQL expression.
+	pub params: Vec<Value>,
+}
+impl Clause {
+	/// Creates a clause that has parameters bound to it.
+	pub fn new<S: Into<String>>(sql: S, params: Vec<Value>) -> Self {
+		Self { sql: sql.into(), params }
+	}
+}
+impl Clause {
+	/// Replaces each `?` parameter with its value as an SQL literal.
+	///
+	/// This is needed where SQLite doesn't allow parameters, such as the `WHERE` clause of an index,
+	/// which also doesn't allow subqueries.
+	pub(crate) fn inline(self) -> crate::Result<String> {
+		let mut params = self.params.into_iter();
+		let mut sql = String::with_capacity(self.sql.len());
+		let (mut quote, mut word) = (None, String::new());
+		for c in self.sql.chars() {
+			if quote.is_none() && c.is_ascii_alphabetic() {
+				word.push(c.to_ascii_uppercase());
+			} else if word == "SELECT" {
+				return Err(Error::InvalidIndexFilter("it uses a subquery, such as the ones in contains filters"));
+			} else {
+				word.clear();
+			}
+			match (quote, c) {
+				(Some(q), c) if c == q => quote = None,
+				(Some(_), _) => {},
+				(None, '\'') | (None, '"') => quote = Some(c),
+				(None, '?') => {
+					sql.push_str(&literal(params.next().unwrap_or(Value::Null))?);
+					continue;
+				},
+				_ => {},
+			}
+			sql.push(c);
+		}
+		Ok(sql)
+	}
+}
+impl From<String> for Clause {
+	fn from(sql: String) -> Self { Self { sql, params: Vec::new() } }
+}
+
+/// A value that is bound to a parameter in an SQL statement.
+///
+/// The value is serialised into JSON when this is created
+/// but if that fails, the error is only returned once the value is used in a statement.
+#[derive(Debug)]
+pub struct Param(Result<Value, Arc<serde_json::Error>>);
+impl Param {
+	/// Serialises a value so that it can be compared to the result of `json_extract`.
+	pub fn new<T: Serialize>(value: &T) -> Self {
+		Self(serde_json::to_value(value).map(sql_value).map_err(Arc::new))
+	}
+
+	/// Gets the SQL value, or the error that occurred when serialising it.
+	pub fn value(&self) -> crate::Result<Value> {
+		self.0.clone().map_err(Error::Serialization)
+	}
+}
+
+/// Converts a JSON value into the SQL value that `json_extract` would produce for it.
+pub(crate) fn sql_value(value: serde_json::Value) -> Value {
+	use serde_json::Value as Json;
+	match value {
+		Json::Null => Value::Null,
+		Json::Bool(b) => Value::Integer(b as i64),
+		Json::Number(n) => n.as_i64().map(Value::Integer)
+			.unwrap_or_else(|| Value::Real(n.as_f64().unwrap_or_default())),
+		Json::String(s) => Value::Text(s),
+		json @ Json::Array(_) | json @ Json::Object(_) => Value::Text(json.to_string()),
+	}
+}
+
+/// Formats an SQL value as an SQL literal.
+fn literal(value: Value) -> crate::Result<String> {
+	Ok(match value {
+		Value::Null => "NULL".into(),
+		Value::Integer(i) => i.to_string(),
+		Value::Real(r) if r.is_finite() => format!("{:?}", r),
+		Value::Real(_) => return Err(Error::InvalidIndexFilter("SQL can't have an infinite or NaN number in it")),
+		Value::Text(s) => crate::quote_literal(&s),
+		Value::Blob(b) => format!("X'{}'", b.iter().map(|byte| format!("{:02X}", byte)).collect::<String>()),
+	})
+}
 
 /// A struct that represents AND.
 pub struct And<A, B> {
@@ -53,6 +153,22 @@ pub struct Gte<G, L> {
 	pub lesser: L,
 }
 
+/// A struct that checks whether a JSON array contains values.
+///
+/// Create this using [`Field::contains`], [`Field::contains_any`] or [`Field::contains_all`].
+///
+/// [`Field::contains`]: ../struct.Field.html#method.contains
+/// [`Field::contains_any`]: ../struct.Field.html#method.contains_any
+/// [`Field::contains_all`]: ../struct.Field.html#method.contains_all
+pub struct Contains {
+	/// The JSON path of the array.
+	pub path: String,
+	/// The values that are being checked for.
+	pub values: Vec<Param>,
+	/// Whether the array must contain all of the values or just one of them.
+	pub all: bool,
+}
+
 /// A struct that compares using the SQL `LIKE` comparison.
 pub struct Like<A, S: std::fmt::Display> {
 	/// The variable to be compared.
@@ -70,6 +186,112 @@ pub struct Like<A, S: std::fmt::Display> {
 /// A struct that checks whether a field exists and if that field is not null.
 pub struct Exists<A>(pub A);
 
+/// A struct that checks whether a value is one of the given values.
+pub struct In<A> {
+	/// The variable that is being checked.
+	pub variable: A,
+	/// The values being checked for, as a JSON array.
+	pub values: Param,
+}
+
+/// A struct that checks whether a value is not one of the given values.
+pub struct NotIn<A> {
+	/// The variable that is being checked.
+	pub variable: A,
+	/// The values being checked for, as a JSON array.
+	pub values: Param,
+}
+
+/// A struct that checks whether a value is between two values, inclusive.
+pub struct Between<A> {
+	/// The variable that is being checked.
+	pub variable: A,
+	/// The lowest value allowed.
+	pub low: Param,
+	/// The highest value allowed.
+	pub high: Param,
+}
+
+/// A struct that checks whether a field exists and is null.
+pub struct IsNull<A>(pub A);
+
+/// A struct that checks whether a field does not exist.
+pub struct IsMissing<A>(pub A);
+
+/// A struct that checks whether a value has the given JSON type.
+pub struct IsType<A> {
+	/// The variable that is being checked.
+	pub variable: A,
+	/// The type that the variable must have.
+	pub json_type: crate::JsonType,
+}
+
+/// A full-text search query.
+///
+/// This can be used as a filter to only keep the entries that match the query,
+/// or as a sort to put the most relevant entries first.
+///
+/// Create this using [`Iterator::search`] or [`Iterator::search_by_relevance`].
+///
+/// [`Iterator::search`]: ../struct.Iterator.html#method.search
+/// [`Iterator::search_by_relevance`]: ../struct.Iterator.html#method.search_by_relevance
+#[derive(Clone, Debug)]
+pub struct Search {
+	/// The quoted name of the full-text index.
+	pub index: String,
+	/// The quoted name of the table being searched.
+	pub table: String,
+	/// The FTS5 query.
+	pub query: String,
+}
+
+/// The name of the full-text index of a table.
+pub(crate) fn search_index(table: &str) -> String { format!("{}_search", table) }
+
+/// The name of the table that records the changes made to a table.
+pub(crate) fn changelog(table: &str) -> String { format!("{}_changelog", table) }
+
+/// The name of the index on a generated column.
+pub(crate) fn generated_index(table: &str, column: &str) -> String { format!("{}_{}", table, column) }
+
+/// The JSON paths that have a generated column, and the quoted name of their column.
+pub(crate) type Generated = Arc<Vec<(String, String)>>;
+
+thread_local! {
+	/// The data column of the query being rendered on this thread, and the generated columns that it can use.
+	static RENDERING: RefCell<Option<(String, Generated)>> = const { RefCell::new(None) };
+}
+
+/// Renders part of a query so that the fields of the data column that have a generated column use the column instead.
+pub(crate) fn with_generated<T>(data_key: &str, generated: &Generated, render: impl FnOnce() -> T) -> T {
+	/// Puts back what was being rendered before, even if rendering panics.
+	struct Restore(Option<(String, Generated)>);
+	impl Drop for Restore {
+		fn drop(&mut self) { RENDERING.with(|rendering| *rendering.borrow_mut() = self.0.take()); }
+	}
+	if generated.is_empty() {
+		return render();
+	}
+	let _restore = Restore(RENDERING.with(|rendering| rendering.replace(Some((data_key.into(), generated.clone())))));
+	render()
+}
+
+/// Gets the generated column that holds a field of the data column, if the query being rendered can use one.
+pub(crate) fn generated_for(data_key: &str, path: &str) -> Option<String> {
+	RENDERING.with(|rendering| match &*rendering.borrow() {
+		Some((key, generated)) if key == data_key =>
+			generated.iter().find(|(field, _)| field == path).map(|(_, column)| column.clone()),
+		_ => None,
+	})
+}
+
+/// Locks a mutex even if it is poisoned.
+///
+/// None of the crate's locks are held while something that could panic runs, so their values are still valid.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
 /// The order which the key will be sorted by
 pub enum SortOrder<T> {
 	/// Lowest value first